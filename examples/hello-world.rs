@@ -12,7 +12,7 @@ fn main() {
 }
 
 fn do_rustbox() {
-    let mut rustbox = match RustBox::init(Default::default()) {
+    let rustbox = match RustBox::init(Default::default()) {
         Result::Ok(v) => v,
         Result::Err(e) => panic!("{}", e),
     };
@@ -27,7 +27,7 @@ fn do_rustbox() {
                   "Press 'q' to quit.");
     loop {
         match rustbox.poll_event(false) {
-            Ok(rustbox::Event::KeyEvent(key)) => {
+            Ok(rustbox::Event::KeyEvent(key, _)) => {
                 match key {
                     Some(Key::Char('q')) => { break; },
                     _ => { }
@@ -0,0 +1,49 @@
+extern crate rustbox;
+
+use std::default::Default;
+use std::io::{self, BufRead};
+
+use rustbox::{Color, RustBox, InitOptions, Tty};
+use rustbox::Key;
+
+// Try it with: ls | cargo run --example picker | xargs echo picked
+fn main() {
+    let stdin = io::stdin();
+    let candidates: Vec<String> = stdin.lock().lines().filter_map(|line| line.ok()).collect();
+
+    if let Some(choice) = pick(&candidates) {
+        println!("{}", choice);
+    }
+}
+
+fn pick(candidates: &[String]) -> Option<String> {
+    // stdin and stdout are pipes, so draw on the terminal itself.
+    let rustbox = match RustBox::init(InitOptions { tty: Tty::Controlling, ..Default::default() }) {
+        Result::Ok(v) => v,
+        Result::Err(e) => panic!("{}", e),
+    };
+
+    let mut selected = 0;
+    loop {
+        rustbox.clear();
+        for (i, candidate) in candidates.iter().enumerate().take(rustbox.height()) {
+            let (fg, bg) = if i == selected { (Color::Black, Color::White) } else { (Color::White, Color::Black) };
+            rustbox.print(0, i, rustbox::RB_NORMAL, fg, bg, candidate);
+        }
+        rustbox.present();
+
+        match rustbox.poll_event(false) {
+            Ok(rustbox::Event::KeyEvent(key, _)) => {
+                match key {
+                    Some(Key::Up) if selected > 0 => { selected -= 1; }
+                    Some(Key::Down) if selected + 1 < candidates.len() => { selected += 1; }
+                    Some(Key::Enter) => { return candidates.get(selected).cloned(); }
+                    Some(Key::Esc) | Some(Key::Char('q')) => { return None; }
+                    _ => { }
+                }
+            },
+            Err(e) => panic!("{}", e),
+            _ => { }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+extern crate time;
+extern crate rustbox;
+
+use std::default::Default;
+
+use rustbox::{Cell, Color, RustBox, InitOptions, Viewport};
+use rustbox::Key;
+
+const CRATES: [&'static str; 8] = ["libc", "time", "bitflags", "num", "termbox-sys", "winapi", "kernel32", "rustbox"];
+
+// A progress panel below the shell prompt, with finished steps printed above it.
+fn main() {
+    let rustbox = match RustBox::init(InitOptions { viewport: Viewport::Inline(2), ..Default::default() }) {
+        Result::Ok(v) => v,
+        Result::Err(e) => panic!("{}", e),
+    };
+
+    for (done, name) in CRATES.iter().enumerate() {
+        draw_progress(&rustbox, done, name);
+        rustbox.present();
+
+        match rustbox.peek_event(time::Duration::milliseconds(500), false) {
+            Ok(rustbox::Event::KeyEvent(Some(Key::Char('q')), _)) => { return; }
+            Err(e) => panic!("{}", e),
+            _ => { }
+        }
+
+        draw_progress(&rustbox, done + 1, name);
+        rustbox.insert_before(&[line(&format!("   Compiled {}", name), Color::Green)]);
+    }
+}
+
+fn draw_progress(rb: &RustBox, done: usize, name: &str) {
+    let width = rb.width();
+    let filled = width * done / CRATES.len();
+
+    rb.clear();
+    rb.print(0, 0, rustbox::RB_BOLD, Color::White, Color::Default, &format!("Building {}...", name));
+    for x in 0..width {
+        let bg = if x < filled { Color::Blue } else { Color::Default };
+        rb.print_char(x, 1, rustbox::RB_NORMAL, Color::White, bg, ' ');
+    }
+}
+
+fn line(text: &str, fg: Color) -> Vec<Cell> {
+    text.chars().map(|ch| Cell { ch: ch, fg: fg, bg: Color::Default, sty: rustbox::RB_NORMAL }).collect()
+}
@@ -13,6 +13,9 @@ fn main()
         Result::Err(e) => panic!("{}", e),
     };
 
+    // Redraw at a steady rate, however much input arrives in between.
+    let frame = rustbox.start_repeating_timer(time::Duration::milliseconds(30));
+
     loop {
         let width = rustbox.width();
         let height = rustbox.height();
@@ -29,18 +32,17 @@ fn main()
             draw_rect(&mut rustbox, x1-2, y1-2, x2+2, y2+2, Color::Black);
         }
 
-        match rustbox.peek_event(time::Duration::milliseconds(30), false) {
-            Ok(rustbox::Event::KeyEvent(key)) => {
+        match rustbox.poll_event(false) {
+            Ok(rustbox::Event::KeyEvent(key, _)) => {
                 match key {
                     Some(Key::Char('q')) => { break; }
                     _ => { }
                 }
             },
+            Ok(rustbox::Event::Timer(id)) if id == frame => { rustbox.present(); },
             Err(e) => panic!("{}", e),
             _ => { }
         }
-
-        rustbox.present();
     }
 }
 
@@ -6,20 +6,19 @@
 extern crate libc;
 extern crate num;
 extern crate time;
+#[cfg(feature="log")]
+extern crate log;
+#[cfg(all(unix, feature="stream"))]
+extern crate futures;
+#[cfg(all(unix, feature="stream"))]
+extern crate tokio_core;
+#[cfg(all(unix, feature="stream"))]
+extern crate mio;
 
 #[macro_use]
 extern crate bitflags;
 
 pub use rustbox::*;
 
-#[cfg(all(target_os="linux"))]
-#[path="rustbox-c/mod.rs"]
-pub mod rustbox;
-
-#[cfg(all(target_os="macos"))]
-#[path="rustbox-c/mod.rs"]
-pub mod rustbox;
-
-#[cfg(all(target_os="windows"))]
 #[path="rustbox-pure/mod.rs"]
 pub mod rustbox;
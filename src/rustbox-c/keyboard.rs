@@ -1,24 +1,4 @@
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub enum Key {
-    Tab,
-    Enter,
-    Esc,
-    Backspace,
-    Right,
-    Left,
-    Up,
-    Down,
-    Delete,
-
-    Home,
-    End,
-    PageUp,
-    PageDown,
-
-    Char(char),
-    Ctrl(char),
-    F(u32),
-}
+use rustbox::event::Key;
 
 impl Key {
     pub fn from_code(code: u16) -> Option<Key> {
@@ -57,6 +37,7 @@ impl Key {
             65515 => Some(Key::Left),
             65516 => Some(Key::Down),
             65517 => Some(Key::Up),
+            65523 => Some(Key::Insert),
             65522 => Some(Key::Delete),
             65535 => Some(Key::F(1)),
             65534 => Some(Key::F(2)),
@@ -1,123 +1,31 @@
 extern crate termbox_sys as termbox;
-extern crate gag;
 
 pub mod keyboard;
 pub mod mouse;
 
-pub use self::keyboard::Key;
-pub use self::mouse::Mouse;
-pub use self::running::running;
-pub use self::style::{Style, RB_BOLD, RB_UNDERLINE, RB_REVERSE, RB_NORMAL};
-
 use self::termbox::RawEvent;
-use self::gag::Hold;
 
-use std::default::Default;
-use std::error::Error;
-use std::{fmt, io, char};
+use rustbox::{InitError, InitOptions, InputMode, Tty, Viewport};
+use rustbox::backend::{Backend, Size, Location};
+use rustbox::cell::Cell;
+use rustbox::event::{Event, EventResult, Key, Mouse, Modifiers, MOD_ALT};
+use rustbox::style::{self, Style};
+
+use std::char;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use num::FromPrimitive;
 use libc::c_int;
-use time::Duration;
-
-#[derive(Clone, Copy)]
-pub enum Event {
-    KeyEventRaw(u8, u16, u32),
-    KeyEvent(Option<Key>),
-    ResizeEvent(i32, i32),
-    MouseEvent(Mouse, i32, i32),
-    NoEvent
-}
-
-#[derive(Clone, Copy, Debug)]
-pub enum InputMode {
-    Current = 0x00,
+use time::{Duration, SteadyTime};
 
-    /// When ESC sequence is in the buffer and it doesn't match any known
-    /// ESC sequence => ESC means TB_KEY_ESC
-    Esc     = 0x01,
-    /// When ESC sequence is in the buffer and it doesn't match any known
-    /// sequence => ESC enables TB_MOD_ALT modifier for the next keyboard event.
-    Alt     = 0x02,
-    /// Same as `Esc` but enables mouse events
-    EscMouse = 0x05,
-    /// Same as `Alt` but enables mouse events
-    AltMouse = 0x06
-}
-
-#[derive(Clone, Copy, PartialEq)]
-#[repr(C,u16)]
-pub enum Color {
-    Default =  0x00,
-    Black =    0x01,
-    Red =      0x02,
-    Green =    0x03,
-    Yellow =   0x04,
-    Blue =     0x05,
-    Magenta =  0x06,
-    Cyan =     0x07,
-    White =    0x08,
-}
-
-mod style {
-    bitflags! {
-        #[repr(C)]
-        flags Style: u16 {
-            const TB_NORMAL_COLOR = 0x000F,
-            const RB_BOLD = 0x0100,
-            const RB_UNDERLINE = 0x0200,
-            const RB_REVERSE = 0x0400,
-            const RB_NORMAL = 0x0000,
-            const TB_ATTRIB = RB_BOLD.bits | RB_UNDERLINE.bits | RB_REVERSE.bits,
-        }
-    }
+/// How often wakeups are checked while waiting for input, in milliseconds.
+const WAKE_CHECK_MS: i64 = 50;
 
-    impl Style {
-        pub fn from_color(color: super::Color) -> Style {
-            Style { bits: color as u16 & TB_NORMAL_COLOR.bits }
-        }
-    }
-}
+/// Set in `emod` for keys pressed with Alt, in InputMode::Alt.
+const TB_MOD_ALT: u8 = 0x01;
 
 const NIL_RAW_EVENT: RawEvent = RawEvent { etype: 0, emod: 0, key: 0, ch: 0, w: 0, h: 0, x: 0, y: 0 };
 
-#[derive(Debug)]
-pub enum EventError {
-   TermboxError,
-   Unknown(isize),
-}
-
-impl fmt::Display for EventError {
-   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-      write!(fmt, "{}", self.description())
-   }
-}
-
-impl Error for EventError {
-   fn description(&self) -> &str {
-      match *self {
-         EventError::TermboxError => "Error in Termbox",
-         // I don't know how to format this without lifetime error.
-         // EventError::Unknown(n) => &format!("There was an unknown error. Error code: {}", n),
-         EventError::Unknown(_) => "Unknown error in Termbox",
-      }
-   }
-}
-
-impl FromPrimitive for EventError {
-   fn from_i64(n: i64) -> Option<EventError> {
-      match n {
-         -1 => Some(EventError::TermboxError),
-         n => Some(EventError::Unknown(n as isize)),
-      }
-   }
-
-   fn from_u64(n: u64) -> Option<EventError> {
-      Some(EventError::Unknown(n as isize))
-   }
-}
-
-pub type EventResult = Result<Event, EventError>;
-
 /// Unpack a RawEvent to an Event
 ///
 /// if the `raw` parameter is true, then the Event variant will be the raw
@@ -137,7 +45,12 @@ fn unpack_event(ev_type: c_int, ev: &RawEvent, raw: bool) -> EventResult {
                     0 => char::from_u32(ev.ch).map(|c| Key::Char(c)),
                     a => Key::from_code(a),
                 };
-                Event::KeyEvent(k)
+                // Termbox only reports Alt, and only in InputMode::Alt.
+                let mut modifiers = Modifiers::implied_by(k);
+                if ev.emod & TB_MOD_ALT != 0 {
+                    modifiers.insert(MOD_ALT);
+                }
+                Event::KeyEvent(k, modifiers)
             }),
         2 => Ok(Event::ResizeEvent(ev.w, ev.h)),
         3 => {
@@ -149,255 +62,156 @@ fn unpack_event(ev_type: c_int, ev: &RawEvent, raw: bool) -> EventResult {
     }
 }
 
-#[derive(Debug)]
-pub enum InitError {
-    BufferStderrFailed(io::Error),
-    AlreadyOpen,
-    UnsupportedTerminal,
-    FailedToOpenTTy,
-    PipeTrapError,
-    Unknown(isize),
+/// Backend wrapping the termbox C library.
+#[allow(missing_copy_implementations)]
+pub struct Termbox {
+    // Input mode to select again when resuming, while suspended.
+    suspended_mode: Option<c_int>,
+    // Whether termbox is shut down, shared with the panic restorer. Shutting it down twice
+    // aborts the process.
+    shut_down: Arc<AtomicBool>,
+    // Set by the waker, from any thread.
+    woken: Arc<AtomicBool>
 }
 
-impl fmt::Display for InitError {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}", self.description())
+// Termbox is not thread safe
+impl !Send for Termbox {}
+
+impl Termbox {
+    pub fn new() -> Termbox {
+        Termbox {
+            suspended_mode: None,
+            shut_down: Arc::new(AtomicBool::new(true)),
+            woken: Arc::new(AtomicBool::new(false))
+        }
     }
 }
 
-impl Error for InitError {
-    fn description(&self) -> &str {
-        match *self {
-            InitError::BufferStderrFailed(_) => "Could not redirect stderr",
-            InitError::AlreadyOpen => "RustBox is already open",
-            InitError::UnsupportedTerminal => "Unsupported terminal",
-            InitError::FailedToOpenTTy => "Failed to open TTY",
-            InitError::PipeTrapError => "Pipe trap error",
-            InitError::Unknown(_) => "Unknown error from Termbox",
+impl Backend for Termbox {
+    fn begin_display(&mut self, opts: &InitOptions) -> Result<(), InitError> {
+        // Termbox always opens the controlling terminal itself.
+        match opts.tty {
+            Tty::Stdio | Tty::Controlling => (),
+            _ => return Err(InitError::UnsupportedTerminal)
         }
-    }
 
-    fn cause(&self) -> Option<&Error> {
-        match *self {
-            InitError::BufferStderrFailed(ref e) => Some(e),
-            _ => None
+        // Termbox always takes over the whole screen and only waits for input. Without the kitty
+        // keyboard protocol or bracketed paste, keys and pastes arrive as they did before.
+        if opts.viewport != Viewport::Fullscreen || opts.signal_events {
+            return Err(InitError::UnsupportedTerminal);
         }
-    }
-}
-
-impl FromPrimitive for InitError {
-   fn from_i64(n: i64) -> Option<InitError> {
-      match n {
-         -1 => Some(InitError::UnsupportedTerminal),
-         -2 => Some(InitError::FailedToOpenTTy),
-         -3 => Some(InitError::PipeTrapError),
-         n => Some(InitError::Unknown(n as isize)),
-      }
-   }
-
-   fn from_u64(n: u64) -> Option<InitError> {
-      Some(InitError::Unknown(n as isize))
-   }
-}
-
-#[allow(missing_copy_implementations)]
-pub struct RustBox {
-    // We only bother to redirect stderr for the moment, since it's used for panic!
-    _stderr: Option<Hold>,
-    // RAII lock.
-    //
-    // Note that running *MUST* be the last field in the destructor, since destructors run in
-    // top-down order. Otherwise it will not properly protect the above fields.
-    _running: running::RunningGuard,
-}
-
-// Termbox is not thread safe
-impl !Send for RustBox {}
-
-#[derive(Clone, Copy,Debug)]
-pub struct InitOptions {
-    /// Use this option to initialize with a specific input mode
-    ///
-    /// See InputMode enum for details on the variants.
-    pub input_mode: InputMode,
-
-    /// Use this option to automatically buffer stderr while RustBox is running.  It will be
-    /// written when RustBox exits.
-    ///
-    /// This option uses a nonblocking OS pipe to buffer stderr output.  This means that if the
-    /// pipe fills up, subsequent writes will fail until RustBox exits.  If this is a concern for
-    /// your program, don't use RustBox's default pipe-based redirection; instead, redirect stderr
-    /// to a log file or another process that is capable of handling it better.
-    pub buffer_stderr: bool,
-}
 
-impl Default for InitOptions {
-    fn default() -> Self {
-        InitOptions {
-            input_mode: InputMode::Current,
-            buffer_stderr: false,
+        match unsafe { termbox::tb_init() } {
+            0 => {
+                self.shut_down.store(false, Ordering::SeqCst);
+                Ok(())
+            }
+            res => Err(FromPrimitive::from_isize(res as isize).unwrap())
         }
     }
-}
-
-mod running {
-    use std::sync::atomic::{self, AtomicBool};
-
-    // The state of the RustBox is protected by the lock. Yay, global state!
-    static RUSTBOX_RUNNING: AtomicBool = atomic::ATOMIC_BOOL_INIT;
 
-    /// true iff RustBox is currently running. Beware of races here--don't rely on this for anything
-    /// critical unless you happen to know that RustBox cannot change state when it is called (a good
-    /// usecase would be checking to see if it's worth risking double printing backtraces to avoid
-    /// having them swallowed up by RustBox).
-    pub fn running() -> bool {
-        RUSTBOX_RUNNING.load(atomic::Ordering::SeqCst)
+    fn finish_display(&mut self) {
+        if !self.shut_down.swap(true, Ordering::SeqCst) {
+            unsafe { termbox::tb_shutdown(); }
+        }
     }
 
-    // Internal RAII guard used to ensure we release the running lock whenever we acquire it.
-    #[allow(missing_copy_implementations)]
-    pub struct RunningGuard(());
-
-    pub fn run() -> Option<RunningGuard> {
-        // Ensure that we are not already running and simultaneously set RUSTBOX_RUNNING using an
-        // atomic swap. This ensures that contending threads don't trample each other.
-        if RUSTBOX_RUNNING.swap(true, atomic::Ordering::SeqCst) {
-            // The Rustbox was already running.
-            None
-        } else {
-            // The RustBox was not already running, and now we have the lock.
-            Some(RunningGuard(()))
+    fn size(&self) -> Size {
+        unsafe {
+            Size {width: termbox::tb_width() as usize, height: termbox::tb_height() as usize}
         }
     }
 
-    impl Drop for RunningGuard {
-        fn drop(&mut self) {
-            // Indicate that we're free now. We could probably get away with lower atomicity here,
-            // but there's no reason to take that chance.
-            RUSTBOX_RUNNING.store(false, atomic::Ordering::SeqCst);
+    fn write_cells(&mut self, cells: &[Cell], origin: Location) {
+        for (i, cell) in cells.iter().enumerate() {
+            let fg = Style::from_color(cell.fg) | (cell.sty & style::TB_ATTRIB);
+            let bg = Style::from_color(cell.bg);
+            unsafe {
+                termbox::tb_change_cell((origin.x + i) as c_int, origin.y as c_int,
+                                        cell.ch as u32, fg.bits(), bg.bits());
+            }
         }
     }
-}
-
-impl RustBox {
-    /// Initialize rustbox.
-    ///
-    /// For the default options, you can use:
-    ///
-    /// ```
-    /// use rustbox::RustBox;
-    /// use std::default::Default;
-    /// let rb = RustBox::init(Default::default());
-    /// ```
-    ///
-    /// Otherwise, you can specify:
-    ///
-    /// ```
-    /// use rustbox::{RustBox, InitOptions};
-    /// use std::default::Default;
-    /// let rb = RustBox::init(InitOptions { input_mode: rustbox::InputMode::Esc, ..Default::default() });
-    /// ```
-    pub fn init(opts: InitOptions) -> Result<RustBox, InitError> {
-        let running = match running::run() {
-            Some(r) => r,
-            None => return Err(InitError::AlreadyOpen),
-        };
 
-        let stderr = if opts.buffer_stderr {
-            Some(try!(Hold::stderr().map_err(|e| InitError::BufferStderrFailed(e))))
-        } else {
-            None
-        };
+    fn flush(&mut self) {
+        unsafe { termbox::tb_present(); }
+    }
 
-        // Create the RustBox.
-        let rb = unsafe { match termbox::tb_init() {
-            0 => RustBox {
-                _stderr: stderr,
-                _running: running,
-            },
-            res => {
-                return Err(FromPrimitive::from_isize(res as isize).unwrap())
+    fn suspend(&mut self) {
+        if !self.shut_down.swap(true, Ordering::SeqCst) {
+            unsafe {
+                // Mode 0 only queries the current one.
+                self.suspended_mode = Some(termbox::tb_select_input_mode(0));
+                termbox::tb_shutdown();
             }
-        }};
-        match opts.input_mode {
-            InputMode::Current => (),
-            _ => rb.set_input_mode(opts.input_mode),
         }
-        Ok(rb)
     }
 
-    pub fn width(&self) -> usize {
-        unsafe { termbox::tb_width() as usize }
+    fn resume(&mut self) {
+        if let Some(mode) = self.suspended_mode.take() {
+            unsafe {
+                if termbox::tb_init() == 0 {
+                    termbox::tb_select_input_mode(mode);
+                    self.shut_down.store(false, Ordering::SeqCst);
+                }
+            }
+        }
     }
 
-    pub fn height(&self) -> usize {
-        unsafe { termbox::tb_height() as usize }
-    }
+    fn panic_restorer(&self) -> Option<Box<Fn() + Send + Sync>> {
+        let shut_down = self.shut_down.clone();
 
-    pub fn clear(&self) {
-        unsafe { termbox::tb_clear() }
+        Some(Box::new(move || {
+            if !shut_down.swap(true, Ordering::SeqCst) {
+                unsafe { termbox::tb_shutdown(); }
+            }
+        }))
     }
 
-    pub fn present(&self) {
-        unsafe { termbox::tb_present() }
+    fn set_cursor(&mut self, location: Option<Location>) {
+        let (x, y) = match location {
+            Some(location) => (location.x as c_int, location.y as c_int),
+            None => (-1, -1)
+        };
+        unsafe { termbox::tb_set_cursor(x, y) }
     }
 
-    pub fn set_cursor(&self, x: isize, y: isize) {
-        unsafe { termbox::tb_set_cursor(x as c_int, y as c_int) }
+    fn waker(&self) -> Option<Box<Fn() + Send + Sync>> {
+        let woken = self.woken.clone();
+        Some(Box::new(move || woken.store(true, Ordering::SeqCst)))
     }
 
-    pub unsafe fn change_cell(&self, x: usize, y: usize, ch: u32, fg: u16, bg: u16) {
-        termbox::tb_change_cell(x as c_int, y as c_int, ch, fg, bg)
-    }
+    fn read_input(&mut self, timeout: Option<Duration>, raw: bool) -> EventResult {
+        let deadline = timeout.map(|timeout| SteadyTime::now() + timeout);
 
-    pub fn print(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
-        let fg = Style::from_color(fg) | (sty & style::TB_ATTRIB);
-        let bg = Style::from_color(bg);
-        for (i, ch) in s.chars().enumerate() {
-            unsafe {
-                self.change_cell(x+i, y, ch as u32, fg.bits(), bg.bits());
+        // Termbox cannot be interrupted while it waits, so it only waits a little at a time.
+        loop {
+            if self.woken.swap(false, Ordering::SeqCst) {
+                return Ok(Event::NoEvent);
             }
-        }
-    }
-
-    pub fn print_char(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
-        let fg = Style::from_color(fg) | (sty & style::TB_ATTRIB);
-        let bg = Style::from_color(bg);
-        unsafe {
-            self.change_cell(x, y, ch as u32, fg.bits(), bg.bits());
-        }
-    }
 
-    pub fn poll_event(&self, raw: bool) -> EventResult {
-        let ev = NIL_RAW_EVENT;
-        let rc = unsafe {
-            termbox::tb_poll_event(&ev as *const RawEvent)
-        };
-        unpack_event(rc, &ev, raw)
-    }
+            let mut wait = Duration::milliseconds(WAKE_CHECK_MS);
+            if let Some(deadline) = deadline {
+                let remaining = deadline - SteadyTime::now();
+                if remaining < wait {
+                    wait = if remaining < Duration::zero() { Duration::zero() } else { remaining };
+                }
+            }
 
-    pub fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
-        let ev = NIL_RAW_EVENT;
-        let rc = unsafe {
-            termbox::tb_peek_event(&ev as *const RawEvent, timeout.num_milliseconds() as c_int)
-        };
-        unpack_event(rc, &ev, raw)
-    }
+            let ev = NIL_RAW_EVENT;
+            let rc = unsafe {
+                termbox::tb_peek_event(&ev as *const RawEvent, wait.num_milliseconds() as c_int)
+            };
 
-    pub fn set_input_mode(&self, mode: InputMode) {
-        unsafe {
-            termbox::tb_select_input_mode(mode as c_int);
+            if rc != 0 || deadline.map_or(false, |deadline| SteadyTime::now() >= deadline) {
+                return unpack_event(rc, &ev, raw);
+            }
         }
     }
-}
 
-impl Drop for RustBox {
-    fn drop(&mut self) {
-        // Since only one instance of the RustBox is ever accessible, we should not
-        // need to do this atomically.
-        // Note: we should definitely have RUSTBOX_RUNNING = true here.
+    fn set_input_mode(&mut self, mode: InputMode) {
         unsafe {
-            termbox::tb_shutdown();
+            termbox::tb_select_input_mode(mode as c_int);
         }
     }
 }
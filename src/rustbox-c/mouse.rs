@@ -1,12 +1,4 @@
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub enum Mouse {
-    Left,
-    Right,
-    Middle,
-    Release,
-    WheelUp,
-    WheelDown
-}
+use rustbox::event::Mouse;
 
 impl Mouse {
     pub fn from_code(code: u16) -> Option<Mouse> {
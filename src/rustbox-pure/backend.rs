@@ -0,0 +1,103 @@
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use time::Duration;
+
+use super::cell::Cell;
+use super::event::EventResult;
+use super::{InitError, InitOptions, InputMode};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub x: usize,
+    pub y: usize
+}
+
+/// The operations RustBox needs from a terminal implementation.
+///
+/// RustBox keeps its own CellBuffer and only hands the backend finished runs of cells, so a
+/// backend does not need to do any buffering of its own. Implement this to drive something
+/// other than the local terminal, then create the RustBox with `RustBox::with_backend`.
+///
+/// Backends that can be waited on in another event loop implement AsRawFd as well, with a
+/// descriptor that becomes readable when read_input has something to return without waiting.
+/// RustBox then implements it too.
+pub trait Backend {
+    /// Take over the display. Called once, before any other method.
+    fn begin_display(&mut self, opts: &InitOptions) -> Result<(), InitError>;
+
+    /// Give the display back. Called once, when the RustBox is dropped.
+    fn finish_display(&mut self);
+
+    /// Size of the visible area, in cells.
+    fn size(&self) -> Size;
+
+    /// Write a run of cells on a single line, starting at `origin`.
+    fn write_cells(&mut self, cells: &[Cell], origin: Location);
+
+    /// Make everything written since the last flush visible. Called at the end of each present.
+    fn flush(&mut self) {}
+
+    /// Move the cursor to `location` and show it, or hide it for `None`.
+    fn set_cursor(&mut self, location: Option<Location>);
+
+    /// Forget anything remembered about the state of the display, because something else may
+    /// have drawn on it. Called before a full redraw.
+    fn reset(&mut self) {}
+
+    /// Give the terminal back temporarily, the way finish_display would, so that another
+    /// program can use it.
+    fn suspend(&mut self) {}
+
+    /// Take the terminal over again after `suspend`. RustBox redraws everything afterwards.
+    fn resume(&mut self) {}
+
+    /// Put `lines` on the terminal above an inline viewport, moving the viewport down or
+    /// scrolling the terminal to make room. The viewport is redrawn in full afterwards.
+    /// Does nothing by default, or when not drawing to an inline viewport.
+    fn insert_before(&mut self, _lines: &[Vec<Cell>]) {}
+
+    /// Wait up to `timeout` for input, or forever if `timeout` is `None`. Returns
+    /// `Event::NoEvent` if nothing arrived in time.
+    ///
+    /// If `raw` is true, key events may be returned as `Event::KeyEventRaw`.
+    fn read_input(&mut self, timeout: Option<Duration>, raw: bool) -> EventResult;
+
+    fn set_input_mode(&mut self, _mode: InputMode) {}
+
+    /// Also wait for `fd` to become readable in read_input, returning `Event::Fd(token)` when
+    /// it is. Watching a descriptor again replaces its token. Returns false if the backend
+    /// cannot wait for other descriptors.
+    #[cfg(unix)]
+    fn watch_fd(&mut self, _fd: RawFd, _token: usize) -> bool {
+        false
+    }
+
+    /// Stop watching `fd`.
+    #[cfg(unix)]
+    fn unwatch_fd(&mut self, _fd: RawFd) {}
+
+    /// A function giving the terminal back like finish_display, but callable from a panic hook
+    /// on any thread. Once it has been called, finish_display must not touch the terminal.
+    fn panic_restorer(&self) -> Option<Box<Fn() + Send + Sync>> {
+        None
+    }
+
+    /// A function making read_input return `Event::NoEvent` soon, callable from any thread.
+    /// If read_input is not waiting, the next call should return straight away. Backends that
+    /// never wait for long need not provide one.
+    fn waker(&self) -> Option<Box<Fn() + Send + Sync>> {
+        None
+    }
+
+    /// Whether this backend drives the process's terminal. Only one RustBox may run on such a
+    /// backend at a time, and `running()` reports whether one is.
+    fn is_terminal(&self) -> bool {
+        true
+    }
+}
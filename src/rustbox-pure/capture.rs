@@ -0,0 +1,256 @@
+use std::sync::{Arc, Mutex};
+
+/// Output captured so far, readable while the capture is running.
+#[derive(Clone)]
+pub struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Captured {
+    #[cfg(unix)]
+    fn new() -> Captured {
+        Captured(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    pub fn text(&self) -> String {
+        let buffer = match self.0.lock() {
+            Ok(buffer) => buffer,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    #[cfg(unix)]
+    fn append(&self, bytes: &[u8]) {
+        let mut buffer = match self.0.lock() {
+            Ok(buffer) => buffer,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        buffer.extend(bytes.iter().cloned());
+    }
+
+    #[cfg(unix)]
+    fn take(&self) -> Vec<u8> {
+        let mut buffer = match self.0.lock() {
+            Ok(buffer) => buffer,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        ::std::mem::replace(&mut *buffer, Vec::new())
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::{Capture, Paused};
+
+/// Stdout and stderr cannot be captured on Windows.
+#[cfg(windows)]
+pub type Capture = ();
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{self, Write};
+    use std::thread::{self, JoinHandle};
+
+    use rustbox::sys::*;
+    use super::Captured;
+
+    /// Redirects stdout or stderr into a pipe, which a background thread drains into memory as
+    /// fast as it is written, so that writers never block or fail because the pipe is full.
+    /// Everything captured is written to the original stream when dropped.
+    pub struct Capture {
+        // The stream being captured, and a duplicate of what it was before.
+        fd: c_int,
+        saved: c_int,
+        // Write end of the pipe telling the drain thread to stop.
+        stop: c_int,
+        captured: Captured,
+        drain: Option<JoinHandle<()>>
+    }
+
+    impl Capture {
+        pub fn stdout() -> io::Result<Capture> {
+            let _ = io::stdout().flush();
+            Capture::stream(1)
+        }
+
+        pub fn stderr() -> io::Result<Capture> {
+            let _ = io::stderr().flush();
+            Capture::stream(2)
+        }
+
+        fn stream(fd: c_int) -> io::Result<Capture> {
+            let data = try!(open_pipe());
+            let stop = match open_pipe() {
+                Ok(stop) => stop,
+                Err(e) => {
+                    close_all(&data);
+                    return Err(e);
+                }
+            };
+
+            let saved = unsafe { dup(fd) };
+            if saved < 0 || unsafe { dup2(data[1], fd) } < 0 {
+                let e = io::Error::last_os_error();
+                if saved >= 0 { unsafe { close(saved); } }
+                close_all(&data);
+                close_all(&stop);
+                return Err(e);
+            }
+
+            // From now on the stream itself is the only write end of the pipe.
+            unsafe { close(data[1]); }
+
+            let captured = Captured::new();
+            let drain_into = captured.clone();
+            let (data_read, stop_read) = (data[0], stop[0]);
+            let drain = thread::spawn(move || drain_pipe(data_read, stop_read, drain_into));
+
+            Ok(Capture { fd: fd, saved: saved, stop: stop[1], captured: captured, drain: Some(drain) })
+        }
+
+        /// The stream being captured.
+        pub fn fd(&self) -> c_int {
+            self.fd
+        }
+
+        pub fn captured(&self) -> Captured {
+            self.captured.clone()
+        }
+
+        /// Put the stream back as it was until the returned guard is dropped, so that a child
+        /// process inherits it rather than the pipe. None if that fails.
+        pub fn pause(&self) -> Option<Paused> {
+            let _ = if self.fd == 1 { io::stdout().flush() } else { io::stderr().flush() };
+
+            let pipe = unsafe { dup(self.fd) };
+            if pipe < 0 || unsafe { dup2(self.saved, self.fd) } < 0 {
+                if pipe >= 0 { unsafe { close(pipe); } }
+                return None;
+            }
+
+            Some(Paused { fd: self.fd, pipe: pipe })
+        }
+    }
+
+    /// Keeps a captured stream as it was before the capture, see `Capture::pause`.
+    pub struct Paused {
+        fd: c_int,
+        // A duplicate of the pipe's write end, to capture into again.
+        pipe: c_int
+    }
+
+    impl Drop for Paused {
+        fn drop(&mut self) {
+            let _ = if self.fd == 1 { io::stdout().flush() } else { io::stderr().flush() };
+
+            unsafe {
+                dup2(self.pipe, self.fd);
+                close(self.pipe);
+            }
+        }
+    }
+
+    impl Drop for Capture {
+        fn drop(&mut self) {
+            let _ = if self.fd == 1 { io::stdout().flush() } else { io::stderr().flush() };
+
+            unsafe {
+                // Put the stream back, then have the drain thread read what is left and stop.
+                // It cannot wait for the end of the pipe, since child processes may still hold
+                // it open.
+                dup2(self.saved, self.fd);
+                close(self.saved);
+
+                let byte: u8 = 0;
+                write(self.stop, &byte as *const u8 as *const c_void, 1);
+            }
+
+            if let Some(drain) = self.drain.take() {
+                let _ = drain.join();
+            }
+            unsafe { close(self.stop); }
+
+            write_all(self.fd, &self.captured.take());
+        }
+    }
+
+    fn open_pipe() -> io::Result<[c_int; 2]> {
+        let mut fds: [c_int; 2] = [0; 2];
+
+        if unsafe { pipe(fds.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(fds)
+    }
+
+    fn close_all(fds: &[c_int]) {
+        for &fd in fds {
+            unsafe { close(fd); }
+        }
+    }
+
+    /// Wait up to `timeout_ms` (forever if negative) for either descriptor to become readable.
+    fn wait(data: c_int, stop: c_int, timeout_ms: c_int) -> (bool, bool) {
+        let mut fds = [
+            pollfd { fd: data, events: POLLIN, revents: 0 },
+            pollfd { fd: stop, events: POLLIN, revents: 0 }
+        ];
+
+        loop {
+            let result = unsafe { poll(fds.as_mut_ptr(), 2, timeout_ms) };
+
+            if result < 0 && io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+                continue;
+            }
+
+            return (fds[0].revents != 0, fds[1].revents != 0);
+        }
+    }
+
+    /// Read a chunk of the pipe into `captured`. Returns false at the end of the pipe.
+    fn read_chunk(data: c_int, captured: &Captured) -> bool {
+        let mut chunk = [0u8; 4096];
+
+        let count = unsafe { read(data, chunk.as_mut_ptr() as *mut c_void, chunk.len() as size_t) };
+
+        if count < 0 {
+            return io::Error::last_os_error().raw_os_error() == Some(EINTR);
+        }
+
+        captured.append(&chunk[..count as usize]);
+        count > 0
+    }
+
+    fn drain_pipe(data: c_int, stop: c_int, captured: Captured) {
+        loop {
+            let (readable, stopping) = wait(data, stop, -1);
+
+            if readable && !read_chunk(data, &captured) {
+                break;
+            }
+
+            if stopping {
+                // Take whatever is still in the pipe, without waiting for more.
+                while wait(data, stop, 0).0 && read_chunk(data, &captured) {}
+                break;
+            }
+        }
+
+        close_all(&[data, stop]);
+    }
+
+    fn write_all(fd: c_int, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let written = unsafe { write(fd, bytes.as_ptr() as *const c_void, bytes.len() as size_t) };
+
+            if written < 0 {
+                if io::Error::last_os_error().raw_os_error() == Some(EINTR) { continue; }
+                return;
+            }
+
+            bytes = &bytes[written as usize..];
+        }
+    }
+}
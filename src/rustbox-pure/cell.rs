@@ -1,6 +1,7 @@
-use std::cmp::{max, min};
-use super::style::{Color, Style};
+use std::cmp::min;
+use super::style::{self, Color, Style};
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Cell
 {
     pub ch: char,
@@ -9,122 +10,123 @@ pub struct Cell
     pub sty: Style
 }
 
-impl Copy for Cell{}
-
-impl Clone for Cell
+impl Default for Cell
 {
-    fn clone(&self) -> Cell { *self }
+    fn default() -> Cell {
+        Cell { ch: ' ', fg: Color::Default, bg: Color::Default, sty: style::RB_NORMAL }
+    }
 }
 
+#[derive(Clone)]
 pub struct CellBuffer
 {
     pub width: usize,
     pub height: usize,
-    pub char_buffer: Vec<u8>,
-    pub attr_buffer: Vec<u16>
+    pub cells: Vec<Cell>
 }
 
 impl CellBuffer
 {
-    pub fn new(width: usize, height: usize, fill_char: u8, fill_attr: u16) -> CellBuffer
+    pub fn new(width: usize, height: usize, fill: Cell) -> CellBuffer
     {
         let buffer_size: usize = width * height;
 
         CellBuffer {
             width: width,
             height: height,
-            char_buffer: vec![fill_char; buffer_size],
-            attr_buffer: vec![fill_attr; buffer_size]
+            cells: vec![fill; buffer_size]
         }
     }
 
-    pub fn resize(&mut self, new_width: usize, new_height: usize, fill_char: u8, fill_attr: u16)
+    pub fn resize(&mut self, new_width: usize, new_height: usize, fill: Cell)
     {
         let old_size = self.height * self.width;
         let new_size = new_width * new_height;
 
         if new_size > old_size {
             // Add extra room in array before any stretch/squeeze.
-            self.char_buffer.resize(new_size, fill_char);
-            self.attr_buffer.resize(new_size, fill_attr);
+            self.cells.resize(new_size, fill);
         }
 
         // If the width has changed in addition to the height, the contents must be shifted
-        if(new_width < self.width) {
-            self.squeeze(new_width, new_height, fill_char, fill_attr);
+        if new_width < self.width {
+            self.squeeze(new_width, new_height, fill);
         }
-        else if(new_width > self.width) {
-            self.stretch(new_width, new_height, fill_char, fill_attr);
+        else if new_width > self.width {
+            self.stretch(new_width, new_height, fill);
         }
 
         if new_size < old_size {
             // Remove extra room in array which is now garbage.
-            self.char_buffer.truncate(new_size);
-            self.attr_buffer.truncate(new_size);
+            self.cells.truncate(new_size);
         }
 
         self.width = new_width;
         self.height = new_height;
     }
 
-    fn stretch(&mut self, new_width: usize, new_height: usize, fill_char: u8, fill_attr: u16)
+    fn stretch(&mut self, new_width: usize, new_height: usize, fill: Cell)
     {
-        let char_buffer_slice = self.char_buffer.as_mut_slice();
-        let attr_buffer_slice = self.attr_buffer.as_mut_slice();
+        let cells = self.cells.as_mut_slice();
 
         for line in (0..min(self.height, new_height)).rev() {
             let dest_index = line * new_width;
             let src_index = line * self.width;
 
             for i in (0..self.width).rev() {
-                char_buffer_slice[dest_index + i] = char_buffer_slice[src_index + i];
-                attr_buffer_slice[dest_index + i] = attr_buffer_slice[src_index + i];
+                cells[dest_index + i] = cells[src_index + i];
             }
             for i in self.width..new_width {
-                char_buffer_slice[dest_index + i] = fill_char;
-                attr_buffer_slice[dest_index + i] = fill_attr;
+                cells[dest_index + i] = fill;
             }
         }
         for line in self.height..new_height
         {
             let dest_index = line * new_width;
             for i in 0..new_width {
-                char_buffer_slice[dest_index + i] = fill_char;
-                attr_buffer_slice[dest_index + i] = fill_attr;
+                cells[dest_index + i] = fill;
             }
         }
     }
 
-    fn squeeze(&mut self, new_width: usize, new_height: usize, fill_char: u8, fill_attr: u16)
+    fn squeeze(&mut self, new_width: usize, new_height: usize, fill: Cell)
     {
-        let char_buffer_slice = self.char_buffer.as_mut_slice();
-        let attr_buffer_slice = self.attr_buffer.as_mut_slice();
+        let cells = self.cells.as_mut_slice();
 
         for line in 0..min(self.height, new_height) {
             let dest_index = line * new_width;
             let src_index = line * self.width;
 
             for i in 0..new_width {
-                char_buffer_slice[dest_index + i] = char_buffer_slice[src_index + i];
-                attr_buffer_slice[dest_index + i] = attr_buffer_slice[src_index + i];
+                cells[dest_index + i] = cells[src_index + i];
             }
         }
         for line in self.height..new_height {
             let dest_index = line * new_width;
 
             for i in 0..new_width {
-                char_buffer_slice[dest_index + i] = fill_char;
-                attr_buffer_slice[dest_index + i] = fill_attr;
+                cells[dest_index + i] = fill;
             }
         }
     }
 
     pub fn resize_blindly(&mut self, new_width: usize, new_height: usize)
     {
-        self.char_buffer.resize(new_width * new_height, 0);
-        self.attr_buffer.resize(new_width * new_height, 0);
+        self.cells.resize(new_width * new_height, Default::default());
 
         self.width = new_width;
         self.height = new_height;
     }
+
+    /// The cell at `x`, `y`, if it is inside the buffer.
+    pub fn get(&self, x: usize, y: usize) -> Option<Cell>
+    {
+        if x < self.width && y < self.height { Some(self.cells[(y * self.width) + x]) } else { None }
+    }
+
+    /// The cells of line `y`.
+    pub fn line(&self, y: usize) -> &[Cell]
+    {
+        &self.cells[(y * self.width)..((y + 1) * self.width)]
+    }
 }
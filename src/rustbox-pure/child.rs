@@ -0,0 +1,44 @@
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use super::capture::Capture;
+#[cfg(unix)]
+use super::sys::*;
+
+/// Run `command` and wait for it to exit, with stdout and stderr put back as they were for it
+/// if they are being captured, so that it draws on the terminal rather than into `captures`.
+///
+/// Like system(3), SIGINT and SIGQUIT are held off meanwhile, so that Ctrl-c and Ctrl-\ typed
+/// for the program do not end this process as well, leaving the terminal as the program left
+/// it. They are caught rather than ignored, so that the program starts out handling them as
+/// usual. SIGTSTP is left to stop this process along with the program, rather than to the
+/// handler giving the terminal back, which would leave the shell waiting for a process that
+/// is not stopped. The handlers that were in place before are put back afterwards.
+#[cfg(unix)]
+pub fn run(command: &mut Command, captures: &[Capture]) -> io::Result<ExitStatus> {
+    let _paused: Vec<_> = captures.iter().filter_map(|capture| capture.pause()).collect();
+
+    let previous = unsafe {
+        (signal(SIGINT, on_signal as sighandler_t),
+         signal(SIGQUIT, on_signal as sighandler_t),
+         signal(SIGTSTP, SIG_DFL))
+    };
+
+    let status = command.status();
+
+    unsafe {
+        signal(SIGINT, previous.0);
+        signal(SIGQUIT, previous.1);
+        signal(SIGTSTP, previous.2);
+    }
+
+    status
+}
+
+#[cfg(windows)]
+pub fn run(command: &mut Command, _: &[Capture]) -> io::Result<ExitStatus> {
+    command.status()
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_: c_int) {}
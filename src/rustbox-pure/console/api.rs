@@ -16,10 +16,11 @@ pub use self::winapi::{
     STD_INPUT_HANDLE, STD_OUTPUT_HANDLE, ENABLE_MOUSE_INPUT, ENABLE_PROCESSED_INPUT, SB_BOTH,
     COINIT_APARTMENTTHREADED, FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE,
 	MOUSE_MOVED, MOUSE_WHEELED, FROM_LEFT_1ST_BUTTON_PRESSED, RIGHTMOST_BUTTON_PRESSED,
-	LEFT_CTRL_PRESSED, RIGHT_CTRL_PRESSED, VK_TAB, VK_RETURN, VK_ESCAPE, VK_BACK, VK_RIGHT,
-    VK_UP, VK_LEFT, VK_DOWN, VK_DELETE, VK_HOME, VK_END, VK_PRIOR, VK_NEXT, VK_F1, VK_F24,
+	LEFT_CTRL_PRESSED, RIGHT_CTRL_PRESSED, LEFT_ALT_PRESSED, RIGHT_ALT_PRESSED, SHIFT_PRESSED, VK_TAB, VK_RETURN, VK_ESCAPE, VK_BACK, VK_RIGHT,
+    VK_UP, VK_LEFT, VK_DOWN, VK_INSERT, VK_DELETE, VK_HOME, VK_END, VK_PRIOR, VK_NEXT, VK_F1, VK_F24,
 	FOREGROUND_RED, FOREGROUND_GREEN, FOREGROUND_BLUE, FOREGROUND_INTENSITY,
-	BACKGROUND_RED, BACKGROUND_GREEN, BACKGROUND_BLUE, BACKGROUND_INTENSITY
+	BACKGROUND_RED, BACKGROUND_GREEN, BACKGROUND_BLUE, BACKGROUND_INTENSITY, WAIT_OBJECT_0,
+    OPEN_EXISTING
 };
 
 pub use self::kernel32::{
@@ -41,6 +42,8 @@ pub use self::kernel32::{
     ReadConsoleInputW,
     CreateConsoleScreenBuffer,
     SetConsoleActiveScreenBuffer,
+    WaitForSingleObject,
+    CreateFileA,
     CloseHandle
 };
 
@@ -67,6 +70,8 @@ extern "system" {
     pub fn SetConsoleFont(hOutput: HANDLE, fontIndex: DWORD);
 
     pub fn GetConsoleFontInfo(hOutput: HANDLE, bMaximize: BOOL, numFonts: DWORD, info: *const CONSOLE_FONT);
+
+    pub fn SetConsoleCtrlHandler(HandlerRoutine: PHANDLER_ROUTINE, Add: BOOL) -> BOOL;
 }
 
 pub struct CONSOLE_FONT {
@@ -76,9 +81,16 @@ pub struct CONSOLE_FONT {
 
 pub type WINEVENTPROC = extern fn(HWINEVENTHOOK, DWORD, HWND, LONG, LONG, DWORD, DWORD);
 
+pub type PHANDLER_ROUTINE = unsafe extern "system" fn(DWORD) -> BOOL;
+
 pub const WINEVENT_OUTOFCONTEXT: DWORD = 0x0000_0000;
 pub const EVENT_SYSTEM_MOVESIZESTART: DWORD = 0x0000_000A;
 pub const EVENT_SYSTEM_MOVESIZEEND: DWORD = 0x0000_000B;
 pub const ESB_DISABLE_BOTH: DWORD = 0x0000_0003;
 pub const ESB_ENABLE_BOTH: DWORD = 0x0000_0000;
 pub const CONSOLE_TEXTMODE_BUFFER: DWORD = 0x0000_0001;
+pub const CTRL_C_EVENT: DWORD = 0;
+pub const CTRL_BREAK_EVENT: DWORD = 1;
+pub const CTRL_CLOSE_EVENT: DWORD = 2;
+pub const CTRL_LOGOFF_EVENT: DWORD = 5;
+pub const CTRL_SHUTDOWN_EVENT: DWORD = 6;
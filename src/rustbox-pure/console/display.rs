@@ -1,34 +1,352 @@
-use rustbox::console::{Handle, Size, Location, DisplayInfo};
-use rustbox::console::wincon;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool, AtomicUsize};
+use time::{Duration, SteadyTime};
 
-pub fn begin_display() -> Handle {
-    let handle = Handle {
-		window: wincon::window_handle(),
-		input: wincon::stdin_buffer(),
-		output: wincon::create_buffer()
-	};
+use rustbox::{InitError, InitOptions, Tty, Viewport};
+use rustbox::backend::{Backend, Size, Location};
+use rustbox::cell::Cell;
+use rustbox::event::{Event, EventResult, Signal};
+use rustbox::style::{self, Color};
+use rustbox::console::Handle;
+use rustbox::console::{wincon, translate};
+use rustbox::console::api::{HANDLE, DWORD, BOOL};
+use rustbox::console::api::{CTRL_C_EVENT, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT};
 
-    // Set console to use alternate output buffer.
-    wincon::set_buffer(handle.output);
+/// How often the window size and wakeups are checked while waiting for input, in milliseconds.
+const RESIZE_CHECK_MS: i64 = 50;
 
-    // Make window resizeable.
-    wincon::set_buffer_size(handle, Size {width: 800, height: 800});
+// Console control event received by on_ctrl_event, plus one. Zero when there is none.
+static CTRL_EVENT: AtomicUsize = atomic::ATOMIC_USIZE_INIT;
 
-    // Disable the scrollbars.
-    wincon::set_scroll_enable(handle, false);
+/// Backend drawing to a separate Windows console screen buffer, or to a region of the
+/// current one for an inline viewport.
+pub struct Console {
+    handle: Option<Handle>,
+    // Buffer to make active again when finished.
+    main_buffer: Option<HANDLE>,
+    // Whether input and main_buffer were opened by begin_display and need closing.
+    opened: bool,
+    // Top row and height of an inline viewport, or None when using a separate buffer.
+    inline: Option<(usize, usize)>,
+    // Size last reported, to tell when the window has been resized.
+    size: Option<Size>,
+    // Whether console control events are reported as Event::Signal.
+    signal_events: bool,
+    // Set by the waker, from any thread.
+    woken: Arc<AtomicBool>
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            handle: None,
+            main_buffer: None,
+            opened: false,
+            inline: None,
+            size: None,
+            signal_events: false,
+            woken: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    fn handle(&self) -> Handle {
+        self.handle.expect("console display has not begun")
+    }
+
+    /// Translate a location in the viewport to one in the screen buffer.
+    fn to_buffer(&self, location: Location) -> Location {
+        match self.inline {
+            Some((top, _)) => Location {x: location.x, y: top + location.y},
+            None => location
+        }
+    }
+
+    /// Find room for an inline viewport of `height` lines starting on the cursor's line, or
+    /// at the end of the buffer if it does not fit there. Returns its top row.
+    fn begin_inline(&self, handle: Handle, height: usize) -> usize {
+        let buffer_size = wincon::buffer_size(handle);
+        let cursor = wincon::cursor_location(handle);
+
+        let top = if cursor.y + height <= buffer_size.height {
+            cursor.y
+        } else {
+            buffer_size.height.saturating_sub(height)
+        };
+
+        // Moving the cursor to the last line scrolls the window to show the whole viewport.
+        if height > 0 {
+            wincon::set_cursor_location(handle, Location {x: 0, y: top + height - 1});
+        }
+
+        // The viewport starts out blank.
+        wincon::fill_character(handle, b' ', buffer_size.width * height, Location {x: 0, y: top});
+
+        top
+    }
+}
+
+impl Backend for Console {
+    fn begin_display(&mut self, opts: &InitOptions) -> Result<(), InitError> {
+        // Only the process's own console can be used.
+        let (input, main_buffer) = match opts.tty {
+            Tty::Stdio => (wincon::stdin_buffer(), wincon::stdout_buffer()),
+            Tty::Controlling => {
+                self.opened = true;
+                (wincon::console_input(), wincon::console_output())
+            }
+            Tty::Path(_) => return Err(InitError::UnsupportedTerminal)
+        };
+
+        let handle = Handle {
+            window: wincon::window_handle(),
+            input: input,
+            output: match opts.viewport {
+                Viewport::Fullscreen => wincon::create_buffer(),
+                Viewport::Inline(_) => main_buffer
+            }
+        };
+
+        match opts.viewport {
+            Viewport::Fullscreen => {
+                // Set console to use alternate output buffer.
+                wincon::set_buffer(handle.output);
+
+                // Make window resizeable.
+                wincon::set_buffer_size(handle, Size {width: 800, height: 800});
+
+                // Disable the scrollbars.
+                wincon::set_scroll_enable(handle, false);
+
+                wincon::set_font(handle, 12);
+            }
+            Viewport::Inline(height) => {
+                self.inline = Some((self.begin_inline(handle, height), height));
+            }
+        }
+
+        // Hide cursor by default.
+        wincon::set_cursor_visible(handle, false);
+
+        // For now enable mouse input, ctrl-c by default
+        wincon::set_mode(handle, true, true);
+
+        if opts.signal_events {
+            wincon::add_ctrl_handler(on_ctrl_event);
+        }
+
+        self.handle = Some(handle);
+        self.main_buffer = Some(main_buffer);
+        self.size = Some(self.size());
+        self.signal_events = opts.signal_events;
+        Ok(())
+    }
+
+    fn finish_display(&mut self) {
+        let handle = self.handle();
+
+        if self.signal_events {
+            wincon::remove_ctrl_handler(on_ctrl_event);
+            self.signal_events = false;
+        }
+
+        match self.inline {
+            // Leave the viewport's contents in place and put the cursor on the line below it.
+            Some((top, _)) => {
+                let below = top + self.size().height;
+                let last_row = wincon::buffer_size(handle).height.saturating_sub(1);
+                let row = if below < last_row { below } else { last_row };
+                wincon::set_cursor_location(handle, Location {x: 0, y: row});
+                wincon::set_cursor_visible(handle, true);
+            }
+            None => {
+                // Restore the original buffer as the displayed buffer.
+                if let Some(main_buffer) = self.main_buffer {
+                    wincon::set_buffer(main_buffer);
+                }
+
+                // Release the handle to the alternate output buffer.
+                wincon::finish_buffer(handle.output);
+            }
+        }
+
+        if self.opened {
+            wincon::finish_buffer(handle.input);
+            if let Some(main_buffer) = self.main_buffer { wincon::finish_buffer(main_buffer); }
+        }
+    }
 
-    // Hide cursor by default.
-    wincon::set_cursor_visible(handle, false);
+    fn size(&self) -> Size {
+        let size = wincon::visible_size(self.handle());
 
-    wincon::set_font(handle, 12);
+        match self.inline {
+            Some((top, height)) => {
+                let below = wincon::buffer_size(self.handle()).height.saturating_sub(top);
+                Size {width: size.width, height: if height < below { height } else { below }}
+            }
+            None => size
+        }
+    }
+
+    fn write_cells(&mut self, cells: &[Cell], origin: Location) {
+        write_buffer(self.handle(), cells, self.to_buffer(origin));
+    }
+
+    fn set_cursor(&mut self, location: Option<Location>) {
+        match location {
+            Some(location) => {
+                wincon::set_cursor_location(self.handle(), self.to_buffer(location));
+                wincon::set_cursor_visible(self.handle(), true);
+            }
+            None => wincon::set_cursor_visible(self.handle(), false)
+        }
+    }
+
+    fn suspend(&mut self) {
+        let handle = self.handle();
+
+        match self.inline {
+            // Let the other program carry on below the viewport.
+            Some((top, _)) => {
+                let below = top + self.size().height;
+                let last_row = wincon::buffer_size(handle).height.saturating_sub(1);
+                let row = if below < last_row { below } else { last_row };
+                wincon::set_cursor_location(handle, Location {x: 0, y: row});
+            }
+            None => {
+                if let Some(main_buffer) = self.main_buffer {
+                    wincon::set_buffer(main_buffer);
+                }
+            }
+        }
+
+        wincon::set_cursor_visible(handle, true);
+        wincon::set_mode(handle, false, true);
+    }
+
+    fn resume(&mut self) {
+        let handle = self.handle();
+
+        match self.inline {
+            Some((_, height)) => {
+                self.inline = Some((self.begin_inline(handle, height), height));
+            }
+            None => wincon::set_buffer(handle.output)
+        }
+
+        wincon::set_cursor_visible(handle, false);
+        wincon::set_mode(handle, true, true);
+    }
+
+    fn insert_before(&mut self, lines: &[Vec<Cell>]) {
+        let (mut top, height) = match self.inline {
+            Some(inline) => inline,
+            None => return
+        };
+        let handle = self.handle();
+        let buffer_size = wincon::buffer_size(handle);
+
+        for line in lines {
+            // Move the viewport down to make room, as long as the buffer is long enough.
+            if top + height >= buffer_size.height {
+                break;
+            }
+
+            let origin = Location {x: 0, y: top};
+            let blank = translate::translate_attr(Color::Default, Color::Default, style::RB_NORMAL);
+            wincon::fill_character(handle, b' ', buffer_size.width, origin);
+            wincon::fill_attribute(handle, blank, buffer_size.width, origin);
+
+            let cells = if line.len() > buffer_size.width { &line[..buffer_size.width] } else { &line[..] };
+            write_buffer(handle, cells, origin);
+
+            top += 1;
+        }
+
+        // Scroll the window along with the viewport.
+        if height > 0 {
+            wincon::set_cursor_location(handle, Location {x: 0, y: top + height - 1});
+        }
+
+        self.inline = Some((top, height));
+    }
+
+    fn waker(&self) -> Option<Box<Fn() + Send + Sync>> {
+        let woken = self.woken.clone();
+        Some(Box::new(move || woken.store(true, atomic::Ordering::SeqCst)))
+    }
+
+    fn read_input(&mut self, timeout: Option<Duration>, _raw: bool) -> EventResult {
+        /* There is currently no error-handling in wincon.rs, so Err result is not used. */
+
+        let deadline = timeout.map(|timeout| SteadyTime::now() + timeout);
+
+        loop {
+            // Resizing the window of a fixed-size screen buffer produces no input event, so its
+            // size is checked between short waits instead.
+            let size = self.size();
+            if self.size != Some(size) {
+                self.size = Some(size);
+                return Ok(Event::ResizeEvent(size.width as i32, size.height as i32));
+            }
+
+            // The control handler runs on a thread of its own, so it is checked the same way.
+            if let Some(signal) = take_ctrl_event() {
+                return Ok(Event::Signal(signal));
+            }
+            if self.woken.swap(false, atomic::Ordering::SeqCst) {
+                return Ok(Event::NoEvent);
+            }
+
+            let mut wait = Duration::milliseconds(RESIZE_CHECK_MS);
+            if let Some(deadline) = deadline {
+                let remaining = deadline - SteadyTime::now();
+                if remaining < wait {
+                    wait = if remaining < Duration::zero() { Duration::zero() } else { remaining };
+                }
+            }
+
+            match wincon::read_input_timeout(self.handle(), wait) {
+                Some(raw_event) => {
+                    if let Some(event) = translate::translate_event(raw_event) {
+                        return Ok(event);
+                    }
+                }
+                None => {
+                    if deadline.map_or(false, |deadline| SteadyTime::now() >= deadline) {
+                        return Ok(Event::NoEvent);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Keeps console control events from ending the process, leaving them for read_input.
+unsafe extern "system" fn on_ctrl_event(ctrl_type: DWORD) -> BOOL {
+    CTRL_EVENT.store(ctrl_type as usize + 1, atomic::Ordering::SeqCst);
+    1
+}
 
-    return handle;
+/// The console control event received since the last call, if any.
+fn take_ctrl_event() -> Option<Signal> {
+    match CTRL_EVENT.swap(0, atomic::Ordering::SeqCst) {
+        0 => None,
+        event => Some(match (event - 1) as DWORD {
+            CTRL_C_EVENT | CTRL_BREAK_EVENT => Signal::Interrupt,
+            CTRL_CLOSE_EVENT => Signal::Hangup,
+            // CTRL_LOGOFF_EVENT or CTRL_SHUTDOWN_EVENT.
+            _ => Signal::Terminate
+        })
+    }
 }
 
-pub fn finish_display(handle: Handle) {
-    // Restore stdout buffer as the displayed buffer.
-    wincon::set_buffer(wincon::stdout_buffer());
+/// Write a run of cells at a location in the screen buffer.
+fn write_buffer(handle: Handle, cells: &[Cell], location: Location) {
+    let characters: Vec<u8> = cells.iter().map(|cell| cell.ch as u8).collect();
+    let attributes: Vec<u16> = cells.iter()
+        .map(|cell| translate::translate_attr(cell.fg, cell.bg, cell.sty))
+        .collect();
 
-    // Release the handle to the alternate output buffer.
-    wincon::finish_buffer(handle.output);
+    wincon::write_characters(handle, characters.as_slice(), location);
+    wincon::write_attributes(handle, attributes.as_slice(), location);
 }
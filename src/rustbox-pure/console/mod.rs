@@ -1,10 +1,40 @@
+#[cfg(target_os="windows")]
 pub mod wincon;
+#[cfg(target_os="windows")]
 pub mod display;
+#[cfg(target_os="windows")]
 pub mod translate;
+#[cfg(target_os="windows")]
 mod api;
 
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+#[path="unix/termios.rs"]
+pub mod termios;
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+#[path="unix/display.rs"]
+pub mod display;
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+#[path="unix/translate.rs"]
+pub mod translate;
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+#[path="unix/signals.rs"]
+pub mod signals;
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+#[path="unix/api.rs"]
+mod api;
+
+#[cfg(target_os="windows")]
 use self::api::{HANDLE, HWND, INPUT_RECORD};
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+use std::os::unix::io::RawFd;
 
+#[cfg(target_os="windows")]
 #[derive(Clone, Copy)]
 pub struct DisplayInfo {
     pub handle: Handle,
@@ -13,6 +43,7 @@ pub struct DisplayInfo {
 	pub display_line: usize
 }
 
+#[cfg(target_os="windows")]
 #[derive(Clone, Copy)]
 pub struct Handle {
 	pub window: HWND,
@@ -20,33 +51,30 @@ pub struct Handle {
 	pub output: HANDLE
 }
 
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
 #[derive(Clone, Copy)]
-pub struct RawEvent {
-    pub record: INPUT_RECORD
+pub struct Handle {
+    pub input: RawFd,
+    pub output: RawFd,
+    /// Terminal attributes in effect before raw mode was entered, restored by finish_display.
+    pub original: api::termios
 }
 
+#[cfg(target_os="windows")]
 #[derive(Clone, Copy)]
-pub struct Size {
-    pub width: usize,
-    pub height: usize
+pub struct RawEvent {
+    pub record: INPUT_RECORD
 }
 
-#[derive(Clone, Copy)]
-pub struct Location {
-    pub x: usize,
-    pub y: usize
-}
+pub use rustbox::backend::{Size, Location};
 
-pub use self::display::{
-    begin_display,
-    finish_display
-};
+pub use self::display::Console;
 
-pub use self::translate::{
-    translate_attr,
-    translate_event
-};
+#[cfg(target_os="windows")]
+pub use self::translate::{translate_event, translate_attr};
 
+#[cfg(target_os="windows")]
 pub use self::wincon::{
     set_mode,
     visible_size,
@@ -54,5 +82,6 @@ pub use self::wincon::{
     write_attributes,
     set_cursor_visible,
     set_cursor_location,
-    read_input
+    read_input,
+    read_input_timeout
 };
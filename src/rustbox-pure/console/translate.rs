@@ -5,7 +5,7 @@ use rustbox::console::api::*;
 
 use rustbox::style;
 use rustbox::style::{Color, Style};
-use rustbox::event::{Event, Mouse, Key};
+use rustbox::event::{Event, Mouse, Key, Modifiers, MOD_ALT, MOD_CTRL, MOD_SHIFT, MOD_NONE};
 
 pub fn translate_event(raw_event: RawEvent) -> Option<Event> {
     match(raw_event.record.EventType as DWORD) {
@@ -43,7 +43,19 @@ fn translate_mouse_event(raw_event: MOUSE_EVENT_RECORD) -> Option<Event> {
 }
 
 fn translate_key_event(raw_event: KEY_EVENT_RECORD) -> Option<Event> {
-	Some(Event::KeyEvent(translate_key_code(raw_event)))
+	let key = translate_key_code(raw_event);
+	Some(Event::KeyEvent(key, translate_modifiers(raw_event.dwControlKeyState) | Modifiers::implied_by(key)))
+}
+
+/// The console does not report the Windows key, so MOD_SUPER is never set.
+fn translate_modifiers(state: DWORD) -> Modifiers {
+	let mut modifiers = MOD_NONE;
+
+	if state & (LEFT_ALT_PRESSED | RIGHT_ALT_PRESSED) != 0 { modifiers.insert(MOD_ALT); }
+	if state & (LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED) != 0 { modifiers.insert(MOD_CTRL); }
+	if state & SHIFT_PRESSED != 0 { modifiers.insert(MOD_SHIFT); }
+
+	modifiers
 }
 
 fn translate_key_code(raw_event: KEY_EVENT_RECORD) -> Option<Key> {
@@ -59,6 +71,7 @@ fn translate_key_code(raw_event: KEY_EVENT_RECORD) -> Option<Key> {
 			VK_LEFT => Some(Key::Left),
 			VK_UP => Some(Key::Up),
 			VK_DOWN => Some(Key::Down),
+			VK_INSERT => Some(Key::Insert),
 			VK_DELETE => Some(Key::Delete),
 			VK_HOME => Some(Key::Home),
 			VK_END => Some(Key::End),
@@ -0,0 +1,102 @@
+#![allow(non_camel_case_types)]
+
+pub use libc::{c_char, c_uint, c_ulong, c_ushort};
+pub use rustbox::sys::*;
+
+/* Declarations below follow the glibc Linux ABI on x86, x86-64, ARM and AArch64, which the
+ * console is only built for: ioctls, signal numbers and open flags differ on MIPS, PowerPC and
+ * SPARC, among others. */
+
+pub type tcflag_t = c_uint;
+pub type cc_t = u8;
+pub type speed_t = c_uint;
+
+pub const NCCS: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct termios {
+    pub c_iflag: tcflag_t,
+    pub c_oflag: tcflag_t,
+    pub c_cflag: tcflag_t,
+    pub c_lflag: tcflag_t,
+    pub c_line: cc_t,
+    pub c_cc: [cc_t; NCCS],
+    pub c_ispeed: speed_t,
+    pub c_ospeed: speed_t
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct winsize {
+    pub ws_row: c_ushort,
+    pub ws_col: c_ushort,
+    pub ws_xpixel: c_ushort,
+    pub ws_ypixel: c_ushort
+}
+
+#[cfg_attr(target_arch="x86_64", repr(C, packed))]
+#[cfg_attr(not(target_arch="x86_64"), repr(C))]
+#[derive(Clone, Copy)]
+pub struct epoll_event {
+    pub events: u32,
+    pub data: u64
+}
+
+pub const STDIN_FILENO: c_int = 0;
+pub const STDOUT_FILENO: c_int = 1;
+
+pub const O_RDWR: c_int = 0o0000002;
+pub const O_NOCTTY: c_int = 0o0000400;
+pub const O_NONBLOCK: c_int = 0o0004000;
+pub const O_CLOEXEC: c_int = 0o2000000;
+
+pub const F_DUPFD_CLOEXEC: c_int = 1030;
+
+pub const TCSANOW: c_int = 0;
+pub const TCSAFLUSH: c_int = 2;
+pub const ISIG: tcflag_t = 0o000001;
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSUSP: usize = 10;
+
+pub const TIOCGWINSZ: c_ulong = 0x5413;
+
+pub const EPOLL_CLOEXEC: c_int = 0o2000000;
+pub const EPOLL_CTL_ADD: c_int = 1;
+pub const EPOLL_CTL_DEL: c_int = 2;
+pub const EPOLLIN: u32 = 0x001;
+
+pub const EIO: c_int = 5;
+pub const EAGAIN: c_int = 11;
+
+pub const SIGHUP: c_int = 1;
+pub const SIGTERM: c_int = 15;
+pub const SIGCONT: c_int = 18;
+pub const SIGWINCH: c_int = 28;
+
+extern "C" {
+    pub fn tcgetattr(fd: c_int, termios_p: *mut termios) -> c_int;
+
+    pub fn tcsetattr(fd: c_int, optional_actions: c_int, termios_p: *const termios) -> c_int;
+
+    pub fn cfmakeraw(termios_p: *mut termios);
+
+    pub fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+
+    pub fn open(pathname: *const c_char, flags: c_int, ...) -> c_int;
+
+    pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+
+    pub fn pipe2(fds: *mut c_int, flags: c_int) -> c_int;
+
+    pub fn raise(sig: c_int) -> c_int;
+
+    pub fn kill(pid: c_int, sig: c_int) -> c_int;
+
+    pub fn epoll_create1(flags: c_int) -> c_int;
+
+    pub fn epoll_ctl(epfd: c_int, op: c_int, fd: c_int, event: *mut epoll_event) -> c_int;
+}
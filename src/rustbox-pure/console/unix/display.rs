@@ -0,0 +1,840 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use time::{Duration, SteadyTime};
+
+use rustbox::{InitError, InitOptions, InputMode, Tty, Viewport};
+use rustbox::backend::{Backend, Size, Location};
+use rustbox::cell::Cell;
+use rustbox::event::{Event, EventError, EventResult, Key, Signal, Modifiers, MOD_ALT, MOD_CTRL, MOD_SUPER};
+use rustbox::input::Decoder;
+use rustbox::keyboard;
+use rustbox::style::{Color, Style};
+use rustbox::console::Handle;
+use rustbox::console::{termios, translate, signals};
+use rustbox::console::signals::SignalPipe;
+use rustbox::console::api::{EIO, SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP, SIGWINCH};
+
+/// DEC private mode that makes the terminal hold off rendering until the end of an update.
+const SYNCHRONIZED_OUTPUT: u32 = 2026;
+
+/// Kitty keyboard protocol flags pushed when enabled: disambiguate keys, report repeats and
+/// releases, report the key with Shift held, and report all keys as escape codes, so that
+/// releases of text keys are reported too.
+const KITTY_KEYBOARD_FLAGS: u32 = 1 | 2 | 4 | 8;
+
+/// How often the size of a terminal other than the controlling one is checked. Resizing it
+/// sends SIGWINCH to the processes of its own session, not to this one.
+const RESIZE_CHECK_MS: i64 = 100;
+
+/// How long the terminal may go quiet in the middle of a paste before its end marker is given
+/// up on, so that input is not taken as pasted text forever if the marker was lost.
+const PASTE_TIMEOUT_MS: i64 = 1000;
+
+/// State shared with the panic restorer, which may run on another thread.
+struct Shared {
+    // Whether the terminal has been given back, and must not be drawn on.
+    given_back: AtomicBool,
+    // Top row of the inline viewport.
+    top: AtomicUsize
+}
+
+/// Backend drawing to a terminal with escape sequences.
+pub struct Console {
+    handle: Option<Handle>,
+    // Descriptor opened by begin_display, as opposed to one handed to us.
+    opened_fd: Option<RawFd>,
+    // Output of the current frame, written to the terminal in one go by flush.
+    output: String,
+    // What the terminal's cursor position and attributes are known to be, so that sequences
+    // setting them to the same thing again can be skipped.
+    position: Option<Location>,
+    attr: Option<(Color, Color, Style)>,
+    cursor_visible: bool,
+    // Whether each frame is wrapped in a synchronized update.
+    synchronized: bool,
+    viewport: Viewport,
+    // Top row and height of an inline viewport, or None when using the whole screen.
+    inline: Option<(usize, usize)>,
+    signals: Option<SignalPipe>,
+    // Descriptors registered by the program, and their tokens.
+    watched: Vec<(RawFd, usize)>,
+    // Readable whenever read_input has something to return, for waiting in another event loop.
+    ready_set: Option<RawFd>,
+    // Whether signals are reported as Event::Signal.
+    signal_events: bool,
+    // Whether mouse reporting is on, see set_input_mode.
+    mouse: bool,
+    // Whether the kitty keyboard protocol is enabled while the terminal is taken over.
+    kitty_keyboard: bool,
+    // Whether bracketed paste is enabled while the terminal is taken over.
+    bracketed_paste: bool,
+    // Decodes what is read from the terminal, keeping sequences and pastes split over several
+    // reads until they are complete.
+    decoder: Decoder,
+    // Events decoded but not returned yet.
+    queued: VecDeque<Event>,
+    // When the paste being read is given up on, while one is.
+    paste_deadline: Option<SteadyTime>,
+    // Whether the terminal was given back because the process was stopped.
+    suspended: bool,
+    // Whether resizes are found by checking the size, for lack of SIGWINCH, and the size
+    // reported last.
+    check_size: bool,
+    reported_size: Size,
+    shared: Arc<Shared>
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            handle: None,
+            opened_fd: None,
+            output: String::new(),
+            position: None,
+            attr: None,
+            cursor_visible: false,
+            synchronized: false,
+            viewport: Viewport::Fullscreen,
+            inline: None,
+            signals: None,
+            watched: Vec::new(),
+            ready_set: None,
+            signal_events: false,
+            mouse: false,
+            kitty_keyboard: false,
+            bracketed_paste: false,
+            decoder: Decoder::new(),
+            queued: VecDeque::new(),
+            paste_deadline: None,
+            suspended: false,
+            check_size: false,
+            reported_size: Size {width: 0, height: 0},
+            shared: Arc::new(Shared { given_back: AtomicBool::new(true), top: AtomicUsize::new(0) })
+        }
+    }
+
+    fn handle(&self) -> Handle {
+        self.handle.expect("console display has not begun")
+    }
+
+    fn open_tty(&mut self, path: &Path) -> Result<(RawFd, RawFd, PathBuf), InitError> {
+        match termios::open_tty(path) {
+            Ok(fd) => {
+                self.opened_fd = Some(fd);
+                Ok((fd, fd, path.to_path_buf()))
+            }
+            Err(_) => Err(InitError::FailedToOpenTTy(path.to_path_buf()))
+        }
+    }
+
+    /// Use stdin and a duplicate of stdout, which keeps working if stdout is captured.
+    fn open_stdio(&mut self) -> Result<(RawFd, RawFd, PathBuf), InitError> {
+        match termios::dup_fd(termios::stdout_fd()) {
+            Ok(fd) => {
+                self.opened_fd = Some(fd);
+                Ok((termios::stdin_fd(), fd, PathBuf::from("/dev/stdin")))
+            }
+            Err(_) => Err(InitError::FailedToOpenTTy(PathBuf::from("/dev/stdout")))
+        }
+    }
+
+    fn move_to(&mut self, location: Location) {
+        if self.position != Some(location) {
+            let top = match self.inline { Some((top, _)) => top, None => 0 };
+            self.output.push_str(&format!("\x1b[{};{}H", top + location.y + 1, location.x + 1));
+            self.position = Some(location);
+        }
+    }
+
+    /// Put the terminal in the state RustBox draws in: raw mode, on the alternate screen or in
+    /// a fresh inline viewport, with the cursor hidden and mouse reporting on.
+    fn enter(&mut self) {
+        let handle = self.handle();
+
+        termios::set_attributes(handle.input, &termios::raw_attributes(&handle.original));
+
+        let viewport = self.viewport;
+        self.inline = match viewport {
+            Viewport::Fullscreen => {
+                // Switch to the alternate screen and start from a blank one.
+                termios::write_bytes(handle, b"\x1b[?1049h\x1b[H\x1b[2J");
+                None
+            }
+            Viewport::Inline(height) => Some((self.begin_inline(handle, height), height))
+        };
+
+        // Hide cursor by default.
+        termios::set_cursor_visible(handle, false);
+        self.cursor_visible = false;
+
+        self.push_modes(handle);
+
+        self.reset();
+        self.share_inline();
+        self.shared.given_back.store(false, Ordering::SeqCst);
+    }
+
+    /// Turn on the input modes RustBox reads in: mouse reporting if the input mode asks for
+    /// it, and the kitty keyboard protocol and bracketed paste when enabled.
+    fn push_modes(&self, handle: Handle) {
+        /* Like termbox, the mouse is left to the terminal unless the input mode asks for it, and
+        Ctrl-c is delivered as Key::Ctrl('c') rather than raising SIGINT. */
+        termios::set_mode(handle, self.mouse, false);
+
+        // The flags are kept per screen, so they are pushed after switching screens.
+        if self.kitty_keyboard {
+            termios::write_bytes(handle, format!("\x1b[>{}u", KITTY_KEYBOARD_FLAGS).as_bytes());
+        }
+        if self.bracketed_paste {
+            termios::write_bytes(handle, b"\x1b[?2004h");
+        }
+    }
+
+    /// Put the terminal back the way it was found, unless that has been done already.
+    fn leave(&mut self) {
+        self.flush();
+
+        if !self.shared.given_back.swap(true, Ordering::SeqCst) {
+            give_back(self.handle(), self.inline, self.kitty_keyboard, self.bracketed_paste);
+        }
+    }
+
+    /// Let the panic restorer know where the inline viewport is now.
+    fn share_inline(&self) {
+        if let Some((top, _)) = self.inline {
+            self.shared.top.store(top, Ordering::SeqCst);
+        }
+    }
+
+    /// Act on the signals reported since the last call. Returns the event to report for them,
+    /// if any.
+    fn handle_signals(&mut self) -> Option<Event> {
+        let mut event = None;
+
+        while let Some(signum) = self.signals.as_ref().and_then(|signals| signals.read()) {
+            match signum {
+                SIGTSTP => {
+                    // Give the terminal back to the shell while stopped.
+                    self.suspend();
+                    signals::stop_self();
+                }
+                SIGCONT => {
+                    if self.suspended {
+                        self.resume();
+                    } else {
+                        // Stopped by something else, which may have changed the terminal mode.
+                        let handle = self.handle();
+                        termios::set_attributes(handle.input, &termios::raw_attributes(&handle.original));
+
+                        // Pop the kitty flags pushed before, if they are still there, so that
+                        // pushing them again does not leave an entry behind on leaving.
+                        if self.kitty_keyboard {
+                            termios::write_bytes(handle, b"\x1b[<u");
+                        }
+                        self.push_modes(handle);
+                        self.reset();
+                    }
+                    event = Some(if self.signal_events { Event::Signal(Signal::Continue) } else { Event::ResumeEvent });
+                }
+                SIGWINCH => {
+                    // The terminal may have reflowed what was on it, so nothing is known about
+                    // it anymore. Resuming redraws everything anyway, so that takes precedence.
+                    self.reset();
+                    if self.suspended {
+                        continue;
+                    }
+                    match event {
+                        None | Some(Event::NoEvent) | Some(Event::ResizeEvent(..)) => {
+                            let size = self.size();
+                            self.reported_size = size;
+                            event = Some(Event::ResizeEvent(size.width as i32, size.height as i32));
+                        }
+                        _ => {}
+                    }
+                }
+                // Written by the waker.
+                0 => {
+                    if event.is_none() {
+                        event = Some(Event::NoEvent);
+                    }
+                }
+                SIGINT | SIGTERM | SIGHUP => {
+                    // SIGHUP may be sent while the terminal is still there, so it is restored
+                    // on exit as usual. If it has gone away, that fails harmlessly.
+                    // Leave any signals after this one in the pipe for the next call, so that
+                    // none are lost.
+                    event = Some(Event::Signal(match signum {
+                        SIGINT => Signal::Interrupt,
+                        SIGTERM => Signal::Terminate,
+                        _ => Signal::Hangup
+                    }));
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        event
+    }
+
+    /// Decode what the terminal has sent, queueing the events. If it ends in what may be the
+    /// start of a sequence, the rest is waited for up to the ESC timeout, after which it is
+    /// taken as it is, so that a lone ESC is the Esc key. Fails once the terminal has gone
+    /// away, after queueing what was read before.
+    fn read_terminal(&mut self, handle: Handle) -> io::Result<()> {
+        let mut buffer = [0; 1024];
+        let mut more = false;
+        let mut result = Ok(());
+
+        // What follows a paste given up on is not part of it.
+        self.end_stale_paste();
+
+        loop {
+            let len = match termios::read_available(handle, &mut buffer, more) {
+                Ok(len) => len,
+                Err(error) => {
+                    result = Err(error);
+                    0
+                }
+            };
+            let events = self.decoder.feed(&buffer[..len]);
+            self.queue(events);
+
+            if len == buffer.len() {
+                // There may be more waiting already.
+                more = false;
+            } else if len > 0 && self.decoder.is_pending() {
+                more = true;
+            } else {
+                break;
+            }
+        }
+
+        if self.decoder.is_pending() {
+            let events = self.decoder.flush();
+            self.queue(events);
+        }
+
+        self.paste_deadline = if self.decoder.is_pasting() {
+            Some(SteadyTime::now() + Duration::milliseconds(PASTE_TIMEOUT_MS))
+        } else {
+            None
+        };
+
+        result
+    }
+
+    /// End the paste being read, with what has arrived of it, if the terminal has been quiet
+    /// for too long since. Returns whether it did.
+    fn end_stale_paste(&mut self) -> bool {
+        match self.paste_deadline {
+            Some(deadline) if self.decoder.is_pasting() && SteadyTime::now() >= deadline => {
+                let events = self.decoder.flush();
+                self.queue(events);
+                self.paste_deadline = None;
+                true
+            }
+            _ => false
+        }
+    }
+
+    /// Decode input read while waiting for a reply to a query, so that keys typed meanwhile are
+    /// not lost.
+    fn take_input(&mut self, bytes: &[u8]) {
+        let events = self.decoder.feed(bytes);
+        self.queue(events);
+
+        // The terminal has gone quiet since.
+        if self.decoder.is_pending() {
+            let events = self.decoder.flush();
+            self.queue(events);
+        }
+    }
+
+    /// Queue decoded events to be returned. With the kitty keyboard protocol, keys the terminal
+    /// driver would raise a signal for, such as Ctrl-z, arrive as sequences it does not act on,
+    /// so the signal is raised here instead. Like the driver, input after them is discarded.
+    fn queue(&mut self, events: Vec<Event>) {
+        if !self.kitty_keyboard {
+            self.queued.extend(events);
+            return;
+        }
+
+        let handle = self.handle();
+        for event in events {
+            let signum = match event {
+                Event::KeyEvent(Some(key), modifiers) | Event::KeyRepeat(Some(key), modifiers) => {
+                    control_byte(key, modifiers).and_then(|byte| termios::signal_for(handle, byte))
+                }
+                _ => None
+            };
+
+            if let Some(signum) = signum {
+                self.queued.clear();
+                signals::raise_in_group(signum);
+                return;
+            }
+            self.queued.push_back(event);
+        }
+    }
+
+    /// The next event, waiting up to `timeout` for one (forever if None).
+    fn next_input(&mut self, timeout: Option<Duration>) -> EventResult {
+        let deadline = timeout.map(|timeout| SteadyTime::now() + timeout);
+
+        loop {
+            // Several events may have been read at once.
+            if let Some(event) = self.queued.pop_front() {
+                return Ok(event);
+            }
+
+            let handle = self.handle();
+            let signal_fd = self.signals.as_ref().map(|signals| signals.fd()).unwrap_or(-1);
+
+            // Waking up for the paste timeout too, if a paste is being read.
+            let now = SteadyTime::now();
+            let remaining = match (deadline, self.paste_deadline) {
+                (Some(deadline), Some(paste)) => Some(cmp::min(deadline, paste) - now),
+                (deadline, paste) => deadline.or(paste).map(|deadline| deadline - now)
+            };
+            let remaining = if self.check_size {
+                let check = Duration::milliseconds(RESIZE_CHECK_MS);
+                Some(remaining.map_or(check, |remaining| cmp::min(remaining, check)))
+            } else {
+                remaining
+            };
+            let ready = termios::wait_any(&self.wait_fds(), remaining);
+
+            if self.check_size && !self.suspended {
+                let size = self.size();
+                if size != self.reported_size {
+                    self.reported_size = size;
+                    self.reset();
+                    return Ok(Event::ResizeEvent(size.width as i32, size.height as i32));
+                }
+            }
+
+            if ready.is_empty() {
+                // The wait may have ended early, for the paste timeout or a size check, or
+                // just a little early.
+                let timed_out = deadline.map_or(false, |deadline| SteadyTime::now() >= deadline);
+                let early = self.paste_deadline.is_some() || self.check_size;
+                if self.end_stale_paste() || (early && !timed_out) {
+                    continue;
+                }
+                return Ok(Event::NoEvent);
+            }
+
+            // A wakeup ends the wait, but whatever else is ready is returned first, so that
+            // it is not left for a wait that may not come.
+            let mut woken = false;
+            if ready.contains(&signal_fd) {
+                match self.handle_signals() {
+                    Some(Event::NoEvent) => woken = true,
+                    Some(event) => return Ok(event),
+                    None => {}
+                }
+            }
+
+            if ready.contains(&handle.input) {
+                let result = self.read_terminal(handle);
+                if let Some(event) = self.queued.pop_front() {
+                    return Ok(event);
+                }
+                // Reported on every call from now on, rather than waiting on a terminal that
+                // only ever reads as closed.
+                if let Err(error) = result {
+                    return Err(EventError::Unknown(error.raw_os_error().unwrap_or(EIO) as isize));
+                }
+            }
+
+            // The terminal comes first, a busy descriptor must not keep keys from being read.
+            for &(fd, token) in self.watched.iter() {
+                if ready.contains(&fd) {
+                    return Ok(Event::Fd(token));
+                }
+            }
+
+            if woken {
+                return Ok(Event::NoEvent);
+            }
+        }
+    }
+
+    /// Descriptors read_input waits for: the terminal, the signal pipe and the watched ones.
+    fn wait_fds(&self) -> Vec<RawFd> {
+        let mut fds = vec![self.handle().input];
+        fds.extend(self.signals.iter().map(|signals| signals.fd()));
+        fds.extend(self.watched.iter().map(|&(fd, _)| fd));
+        fds
+    }
+
+    /// Make room for an inline viewport of `height` lines, starting on the cursor's line and
+    /// scrolling the screen up if there are not enough lines below it. Returns its top row.
+    fn begin_inline(&mut self, handle: Handle, height: usize) -> usize {
+        let rows = termios::visible_size(handle).height;
+        let height = if height < rows { height } else { rows };
+
+        let mut scroll = String::from("\r");
+        for _ in 1..height {
+            scroll.push_str("\n");
+        }
+        if height > 1 {
+            scroll.push_str(&format!("\x1b[{}A", height - 1));
+        }
+        termios::write_bytes(handle, scroll.as_bytes());
+
+        let mut input = Vec::new();
+        let location = termios::cursor_location(handle, &mut input);
+        self.take_input(&input);
+
+        let top = match location {
+            Some(location) => location.y,
+            // No reply, assume the viewport ended up at the bottom of the screen.
+            None => rows - height
+        };
+
+        // Clear whatever was below the cursor, the viewport starts out blank.
+        termios::write_bytes(handle, b"\x1b[J");
+
+        top
+    }
+}
+
+impl Backend for Console {
+    fn begin_display(&mut self, opts: &InitOptions) -> Result<(), InitError> {
+        let (input, output, path) = match opts.tty {
+            Tty::Stdio => try!(self.open_stdio()),
+            Tty::Controlling => try!(self.open_tty(Path::new("/dev/tty"))),
+            Tty::Path(ref path) => try!(self.open_tty(path)),
+            Tty::Fds(input, output) => (input, output, PathBuf::from(format!("/dev/fd/{}", input)))
+        };
+
+        // Fails when the input is not a terminal.
+        let original = match termios::attributes(input) {
+            Ok(attrs) => attrs,
+            Err(_) => {
+                if let Some(fd) = self.opened_fd.take() { termios::close_fd(fd); }
+                return Err(InitError::FailedToOpenTTy(path));
+            }
+        };
+
+        let handle = Handle {
+            input: input,
+            output: output,
+            original: original
+        };
+
+        // Job control signals and resizes are handled while reading input.
+        let mut signals = match SignalPipe::open() {
+            Ok(signals) => signals,
+            Err(_) => {
+                if let Some(fd) = self.opened_fd.take() { termios::close_fd(fd); }
+                return Err(InitError::PipeTrapError);
+            }
+        };
+        signals.handle(SIGTSTP);
+        signals.handle(SIGCONT);
+        signals.handle(SIGWINCH);
+        if opts.signal_events {
+            signals.handle(SIGINT);
+            signals.handle(SIGTERM);
+            signals.handle(SIGHUP);
+        }
+
+        self.handle = Some(handle);
+        self.viewport = opts.viewport;
+        self.signals = Some(signals);
+        self.signal_events = opts.signal_events;
+        self.kitty_keyboard = opts.kitty_keyboard;
+        self.check_size = match opts.tty {
+            Tty::Path(_) | Tty::Fds(..) => true,
+            _ => false
+        };
+        self.bracketed_paste = opts.bracketed_paste;
+        self.ready_set = termios::ready_set(&self.wait_fds()).ok();
+        self.enter();
+        self.reported_size = self.size();
+
+        self.synchronized = match opts.synchronized_output {
+            Some(synchronized) => synchronized,
+            None => {
+                let mut input = Vec::new();
+                let supported = termios::supports_mode(handle, SYNCHRONIZED_OUTPUT, &mut input);
+                self.take_input(&input);
+                supported
+            }
+        };
+
+        Ok(())
+    }
+
+    fn finish_display(&mut self) {
+        self.leave();
+
+        self.signals = None;
+
+        if let Some(set) = self.ready_set.take() {
+            termios::close_fd(set);
+        }
+
+        if let Some(fd) = self.opened_fd.take() {
+            termios::close_fd(fd);
+        }
+    }
+
+    fn size(&self) -> Size {
+        let size = termios::visible_size(self.handle());
+
+        match self.inline {
+            Some((top, height)) => {
+                let below = size.height.saturating_sub(top);
+                Size {width: size.width, height: if height < below { height } else { below }}
+            }
+            None => size
+        }
+    }
+
+    fn write_cells(&mut self, cells: &[Cell], origin: Location) {
+        // Keep the cursor from jumping around the screen while the frame is drawn.
+        if self.cursor_visible {
+            self.output.push_str("\x1b[?25l");
+            self.cursor_visible = false;
+        }
+
+        self.move_to(origin);
+
+        for cell in cells {
+            let attr = (cell.fg, cell.bg, cell.sty);
+            if self.attr != Some(attr) {
+                self.output.push_str(&translate::sgr_sequence(cell.fg, cell.bg, cell.sty));
+                self.attr = Some(attr);
+            }
+
+            // Control characters would move the terminal's cursor.
+            self.output.push(if cell.ch.is_control() { ' ' } else { cell.ch });
+        }
+
+        self.position = Some(Location {x: origin.x + cells.len(), y: origin.y});
+    }
+
+    fn flush(&mut self) {
+        // Drawing on a terminal that was given back would mess up whatever is using it now.
+        if self.shared.given_back.load(Ordering::SeqCst) {
+            self.output.clear();
+        }
+
+        if !self.output.is_empty() {
+            let handle = self.handle();
+
+            if self.synchronized {
+                let frame = format!("\x1b[?{0}h{1}\x1b[?{0}l", SYNCHRONIZED_OUTPUT, self.output);
+                termios::write_bytes(handle, frame.as_bytes());
+            } else {
+                termios::write_bytes(handle, self.output.as_bytes());
+            }
+
+            self.output.clear();
+        }
+    }
+
+    fn set_cursor(&mut self, location: Option<Location>) {
+        match location {
+            Some(location) => {
+                self.move_to(location);
+                self.output.push_str("\x1b[?25h");
+                self.cursor_visible = true;
+            }
+            None => {
+                self.output.push_str("\x1b[?25l");
+                self.cursor_visible = false;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.position = None;
+        self.attr = None;
+    }
+
+    fn suspend(&mut self) {
+        if !self.suspended {
+            self.leave();
+            self.suspended = true;
+        }
+    }
+
+    fn resume(&mut self) {
+        if self.suspended {
+            self.enter();
+            self.suspended = false;
+        }
+    }
+
+    fn insert_before(&mut self, lines: &[Vec<Cell>]) {
+        let (mut top, height) = match self.inline {
+            Some(inline) => inline,
+            None => return
+        };
+        let size = termios::visible_size(self.handle());
+
+        // Lines scrolled in at the bottom take the current background color.
+        self.output.push_str("\x1b[0m");
+        self.attr = None;
+
+        for line in lines {
+            // The line goes where the top of the viewport is now.
+            if top + height < size.height {
+                // There is room below the viewport to move it down.
+            }
+            else if top > 0 {
+                // Scroll everything up, the top line goes into the scrollback.
+                self.output.push_str(&format!("\x1b[{};1H\n", size.height));
+                top -= 1;
+            }
+            else {
+                // The viewport fills the screen, there is nowhere to put the line.
+                break;
+            }
+
+            let cells = if line.len() > size.width { &line[..size.width] } else { &line[..] };
+            self.inline = Some((top, height));
+            self.position = None;
+            self.write_cells(cells, Location {x: 0, y: 0});
+
+            // Clear the rest of the line.
+            self.output.push_str("\x1b[0m\x1b[K");
+            self.attr = None;
+
+            top += 1;
+        }
+
+        self.inline = Some((top, height));
+        self.position = None;
+        self.share_inline();
+    }
+
+    fn panic_restorer(&self) -> Option<Box<Fn() + Send + Sync>> {
+        let handle = self.handle();
+        let viewport = self.viewport;
+        let kitty_keyboard = self.kitty_keyboard;
+        let bracketed_paste = self.bracketed_paste;
+        let shared = self.shared.clone();
+
+        Some(Box::new(move || {
+            if !shared.given_back.swap(true, Ordering::SeqCst) {
+                let inline = match viewport {
+                    Viewport::Inline(height) => Some((shared.top.load(Ordering::SeqCst), height)),
+                    Viewport::Fullscreen => None
+                };
+                give_back(handle, inline, kitty_keyboard, bracketed_paste);
+            }
+        }))
+    }
+
+    fn waker(&self) -> Option<Box<Fn() + Send + Sync>> {
+        self.signals.as_ref().map(|signals| {
+            let waker = signals.waker();
+            Box::new(move || waker.wake()) as Box<Fn() + Send + Sync>
+        })
+    }
+
+    fn watch_fd(&mut self, fd: RawFd, token: usize) -> bool {
+        self.unwatch_fd(fd);
+        if let Some(set) = self.ready_set {
+            let _ = termios::ready_set_add(set, fd);
+        }
+        self.watched.push((fd, token));
+        true
+    }
+
+    fn unwatch_fd(&mut self, fd: RawFd) {
+        if self.watched.iter().any(|&(watched, _)| watched == fd) {
+            if let Some(set) = self.ready_set {
+                termios::ready_set_remove(set, fd);
+            }
+            self.watched.retain(|&(watched, _)| watched != fd);
+        }
+    }
+
+    fn read_input(&mut self, timeout: Option<Duration>, raw: bool) -> EventResult {
+        let event = self.next_input(timeout);
+        if raw { event.map(keyboard::raw_event) } else { event }
+    }
+
+    fn set_input_mode(&mut self, mode: InputMode) {
+        let (alt, mouse) = match mode {
+            InputMode::Current => return,
+            InputMode::Esc => (false, false),
+            InputMode::Alt => (true, false),
+            InputMode::EscMouse => (false, true),
+            InputMode::AltMouse => (true, true)
+        };
+
+        self.decoder.set_alt(alt);
+        self.mouse = mouse;
+        if !self.shared.given_back.load(Ordering::SeqCst) {
+            termios::set_mode(self.handle(), mouse, false);
+        }
+    }
+}
+
+impl AsRawFd for Console {
+    fn as_raw_fd(&self) -> RawFd {
+        // Signals, wakeups and watched descriptors make read_input return too, so waiting for
+        // the terminal alone is not enough.
+        self.ready_set.unwrap_or(self.handle().input)
+    }
+}
+
+/// The character a terminal sends for `key` without the kitty keyboard protocol, if it is a
+/// control character, such as 0x1a for Ctrl-z.
+fn control_byte(key: Key, modifiers: Modifiers) -> Option<u8> {
+    if modifiers.intersects(MOD_ALT | MOD_SUPER) {
+        return None;
+    }
+
+    match key {
+        Key::Ctrl(c) => Some(c as u8 & 0x1f),
+        Key::Char(c @ '@' ... '~') if modifiers.contains(MOD_CTRL) => Some(c as u8 & 0x1f),
+        _ => None
+    }
+}
+
+/// Put the terminal back the way it was found, leaving what was drawn in an inline viewport
+/// (given by its top row and height) on the screen. `kitty_keyboard` tells whether the
+/// kitty keyboard protocol flags were pushed, and need popping, `bracketed_paste` whether
+/// bracketed paste was enabled.
+fn give_back(handle: Handle, inline: Option<(usize, usize)>, kitty_keyboard: bool, bracketed_paste: bool) {
+    // Pop the flags while still on the screen they were pushed on.
+    if kitty_keyboard {
+        termios::write_bytes(handle, b"\x1b[<u");
+    }
+
+    if bracketed_paste {
+        termios::write_bytes(handle, b"\x1b[?2004l");
+    }
+
+    // Disable mouse reporting, reset attributes and show the cursor.
+    termios::set_mode(handle, false, false);
+    termios::write_bytes(handle, b"\x1b[0m\x1b[?25h");
+
+    match inline {
+        // Leave the viewport's contents on screen and put the cursor on the line below it.
+        Some((top, height)) => {
+            let rows = termios::visible_size(handle).height;
+            let last_row = if top + height < rows { top + height } else { rows };
+            termios::write_bytes(handle, format!("\x1b[{};1H\r\n", last_row).as_bytes());
+        }
+        // Leave the alternate screen.
+        None => termios::write_bytes(handle, b"\x1b[?1049l")
+    }
+
+    // Restore the terminal to the mode it was found in.
+    termios::set_attributes(handle.input, &handle.original);
+}
@@ -0,0 +1,146 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool, AtomicIsize};
+
+use rustbox::console::api::*;
+
+// Write end of the pipe signal handlers report to. Zero when there is none, since it can never
+// be the pipe's descriptor while stdin is open.
+static WRITE_FD: AtomicIsize = atomic::ATOMIC_ISIZE_INIT;
+
+/// Turns signals into bytes on a pipe, so that they can be waited for together with input, and
+/// handled outside of a signal handler. A zero byte, which is no signal, is written by a
+/// `Waker`.
+///
+/// Only one can exist at a time, as there is only one RustBox driving a terminal.
+pub struct SignalPipe {
+    pipe: Arc<Pipe>,
+    // Handlers to put back when dropped.
+    previous: Vec<(c_int, sighandler_t)>
+}
+
+/// The descriptors of the pipe, closed once neither the SignalPipe nor any Waker is left.
+struct Pipe {
+    read: c_int,
+    write: c_int,
+    // Whether a wakeup is on the pipe and has not been read yet. Wakeups are coalesced, so
+    // that however many there are they take up one byte of it, and cannot fill it up.
+    wake_pending: AtomicBool
+}
+
+/// Writes wakeups to a SignalPipe, from any thread. The pipe stays open for as long as a Waker
+/// does, so one outliving the SignalPipe writes to a pipe no one reads anymore, rather than to
+/// whatever its descriptor was reused for.
+#[derive(Clone)]
+pub struct Waker(Arc<Pipe>);
+
+impl SignalPipe {
+    pub fn open() -> io::Result<SignalPipe> {
+        let mut fds: [c_int; 2] = [0; 2];
+
+        if unsafe { pipe2(fds.as_mut_ptr(), O_NONBLOCK | O_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        WRITE_FD.store(fds[1] as isize, atomic::Ordering::SeqCst);
+
+        let pipe = Pipe { read: fds[0], write: fds[1], wake_pending: AtomicBool::new(false) };
+        Ok(SignalPipe { pipe: Arc::new(pipe), previous: Vec::new() })
+    }
+
+    pub fn waker(&self) -> Waker {
+        Waker(self.pipe.clone())
+    }
+
+    /// Start reporting `signum` on the pipe instead of handling it as before.
+    pub fn handle(&mut self, signum: c_int) {
+        let previous = unsafe { signal(signum, on_signal as sighandler_t) };
+        self.previous.push((signum, previous));
+    }
+
+    /// Descriptor that becomes readable when a signal has been reported.
+    pub fn fd(&self) -> c_int {
+        self.pipe.read
+    }
+
+    /// Next signal reported, if there is one.
+    pub fn read(&self) -> Option<c_int> {
+        let mut byte: u8 = 0;
+
+        loop {
+            let result = unsafe { read(self.pipe.read, &mut byte as *mut u8 as *mut c_void, 1) };
+
+            if result < 0 && io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+                continue;
+            }
+
+            if result != 1 {
+                return None;
+            }
+
+            // Any wakeup from now on needs a byte of its own.
+            if byte == 0 {
+                self.pipe.wake_pending.store(false, atomic::Ordering::SeqCst);
+            }
+
+            return Some(byte as c_int);
+        }
+    }
+}
+
+impl Drop for SignalPipe {
+    fn drop(&mut self) {
+        for &(signum, previous) in self.previous.iter().rev() {
+            unsafe { signal(signum, previous); }
+        }
+
+        WRITE_FD.store(0, atomic::Ordering::SeqCst);
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.read);
+            close(self.write);
+        }
+    }
+}
+
+impl Waker {
+    /// Report a zero byte on the pipe. Nothing more is written while the last one has not been
+    /// read.
+    pub fn wake(&self) {
+        if !self.0.wake_pending.swap(true, atomic::Ordering::SeqCst) {
+            write_byte(self.0.write, 0);
+        }
+    }
+}
+
+/// Send `signum` to the process group, as the terminal driver does for keys like Ctrl-z.
+pub fn raise_in_group(signum: c_int) {
+    unsafe { kill(0, signum); }
+}
+
+/// Stop the process as SIGTSTP would have by default, returning once it is continued.
+pub fn stop_self() {
+    unsafe {
+        let previous = signal(SIGTSTP, SIG_DFL);
+        raise(SIGTSTP);
+        signal(SIGTSTP, previous);
+    }
+}
+
+extern "C" fn on_signal(signum: c_int) {
+    let fd = WRITE_FD.load(atomic::Ordering::SeqCst) as c_int;
+
+    if fd > 0 {
+        write_byte(fd, signum as u8);
+    }
+}
+
+fn write_byte(fd: c_int, byte: u8) {
+    // The pipe only fills up if signals go unread for a long time, as wakeups take up one byte
+    // at most. A signal is dropped then.
+    unsafe { write(fd, &byte as *const u8 as *const c_void, 1); }
+}
@@ -0,0 +1,349 @@
+use std::io;
+use std::mem;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use time::Duration;
+
+use rustbox::console::{Handle, Size, Location};
+use rustbox::console::api::*;
+
+/// How long to wait for the rest of an escape sequence before treating ESC as a key press.
+const ESC_TIMEOUT_MS: c_int = 25;
+
+/// How long to wait for each byte of a reply to a terminal query.
+const QUERY_TIMEOUT_MS: c_int = 100;
+
+pub fn stdin_fd() -> c_int {
+    STDIN_FILENO
+}
+
+pub fn stdout_fd() -> c_int {
+    STDOUT_FILENO
+}
+
+/// Open a terminal device for reading and writing, without making it the controlling terminal.
+pub fn open_tty(path: &Path) -> io::Result<c_int> {
+    let path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "path contains a nul byte"))
+    };
+
+    let fd = unsafe { open(path.as_ptr(), O_RDWR | O_NOCTTY | O_CLOEXEC) };
+
+    if fd < 0 { Err(io::Error::last_os_error()) } else { Ok(fd) }
+}
+
+/// Duplicate a descriptor, so that it stays usable if the original is redirected.
+pub fn dup_fd(fd: c_int) -> io::Result<c_int> {
+    let dup = unsafe { fcntl(fd, F_DUPFD_CLOEXEC, 0) };
+
+    if dup < 0 { Err(io::Error::last_os_error()) } else { Ok(dup) }
+}
+
+pub fn close_fd(fd: c_int) {
+    unsafe { close(fd); }
+}
+
+/// Create a descriptor that is readable whenever any of `fds` is, which can be waited for in
+/// place of all of them.
+pub fn ready_set(fds: &[c_int]) -> io::Result<c_int> {
+    let set = unsafe { epoll_create1(EPOLL_CLOEXEC) };
+    if set < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    for &fd in fds {
+        if let Err(e) = ready_set_add(set, fd) {
+            close_fd(set);
+            return Err(e);
+        }
+    }
+
+    Ok(set)
+}
+
+pub fn ready_set_add(set: c_int, fd: c_int) -> io::Result<()> {
+    let mut event = epoll_event { events: EPOLLIN, data: fd as u64 };
+
+    if unsafe { epoll_ctl(set, EPOLL_CTL_ADD, fd, &mut event as *mut epoll_event) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+pub fn ready_set_remove(set: c_int, fd: c_int) {
+    let mut event = epoll_event { events: 0, data: 0 };
+    unsafe { epoll_ctl(set, EPOLL_CTL_DEL, fd, &mut event as *mut epoll_event); }
+}
+
+pub fn attributes(fd: c_int) -> io::Result<termios> {
+    let mut attrs: termios = unsafe { mem::zeroed() };
+
+    if unsafe { tcgetattr(fd, &mut attrs as *mut termios) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(attrs)
+}
+
+pub fn set_attributes(fd: c_int, attrs: &termios) {
+    unsafe { tcsetattr(fd, TCSAFLUSH, attrs as *const termios); }
+}
+
+pub fn raw_attributes(original: &termios) -> termios {
+    let mut attrs = *original;
+
+    unsafe { cfmakeraw(&mut attrs as *mut termios); }
+
+    // Block until at least one byte is available, with no inter-byte timer.
+    attrs.c_cc[VMIN] = 1;
+    attrs.c_cc[VTIME] = 0;
+
+    attrs
+}
+
+pub fn set_mode(handle: Handle, enable_mouse: bool, enable_ctrlc: bool) {
+    if enable_mouse {
+        // Button tracking with drag motion, reported in SGR (1006) encoding.
+        write_bytes(handle, b"\x1b[?1000h\x1b[?1002h\x1b[?1006h");
+    }
+    else {
+        write_bytes(handle, b"\x1b[?1006l\x1b[?1002l\x1b[?1000l");
+    }
+
+    if let Ok(mut attrs) = attributes(handle.input) {
+        /* Signal keys stay enabled so that Ctrl-z still suspends, but unless enable_ctrlc is set
+        Ctrl-c and Ctrl-\ are disabled and arrive as keys instead. */
+        attrs.c_lflag = attrs.c_lflag | ISIG;
+        for &index in [VINTR, VQUIT].iter() {
+            attrs.c_cc[index] = if enable_ctrlc { handle.original.c_cc[index] } else { 0 };
+        }
+
+        unsafe { tcsetattr(handle.input, TCSANOW, &attrs as *const termios); }
+    }
+}
+
+/// The signal the terminal driver would raise on reading the character `byte`, given how the
+/// terminal is set up now, if any.
+pub fn signal_for(handle: Handle, byte: u8) -> Option<c_int> {
+    let attrs = match attributes(handle.input) {
+        Ok(attrs) => attrs,
+        Err(_) => return None
+    };
+
+    // A zero character disables the signal.
+    if attrs.c_lflag & ISIG == 0 || byte == 0 {
+        return None;
+    }
+
+    [(VINTR, SIGINT), (VQUIT, SIGQUIT), (VSUSP, SIGTSTP)].iter()
+        .find(|&&(index, _)| attrs.c_cc[index] == byte)
+        .map(|&(_, signum)| signum)
+}
+
+pub fn visible_size(handle: Handle) -> Size {
+    let mut ws = winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+
+    unsafe { ioctl(handle.output, TIOCGWINSZ, &mut ws as *mut winsize); }
+
+    Size {width: ws.ws_col as usize, height: ws.ws_row as usize}
+}
+
+pub fn write_bytes(handle: Handle, bytes: &[u8]) {
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let remaining = &bytes[offset..];
+        let written = unsafe {
+            write(handle.output, remaining.as_ptr() as *const c_void, remaining.len() as size_t)
+        };
+
+        if written < 0 {
+            match io::Error::last_os_error().raw_os_error() {
+                Some(EINTR) | Some(EAGAIN) => continue,
+                _ => return
+            }
+        }
+
+        offset += written as usize;
+    }
+}
+
+pub fn set_cursor_visible(handle: Handle, visible: bool) {
+    write_bytes(handle, if visible { b"\x1b[?25h" } else { b"\x1b[?25l" });
+}
+
+/// Ask the terminal whether it supports a DEC private mode, with a DECRQM request.
+///
+/// The request is followed by a primary device attributes request, which every terminal
+/// answers, so that terminals ignoring DECRQM are recognised without waiting for a timeout.
+/// Anything else read while waiting for the replies, such as keys typed meanwhile, is added
+/// to `input`.
+pub fn supports_mode(handle: Handle, mode: u32, input: &mut Vec<u8>) -> bool {
+    write_bytes(handle, format!("\x1b[?{}$p\x1b[c", mode).as_bytes());
+
+    // Both replies start with ESC [ ?, and the device attributes one, ending in c, comes last.
+    let reply = read_reply(handle.input, b'c', true, input);
+
+    // DECRPM reply: ESC [ ? mode ; Ps $ y, where Ps is 1 (set), 2 (reset) or 3 (permanently
+    // set) if the mode is recognised.
+    let prefix = format!("\x1b[?{};", mode);
+    let reply = String::from_utf8_lossy(&reply);
+
+    match reply.find(&prefix) {
+        Some(index) => match reply[index + prefix.len()..].chars().next() {
+            Some('1') | Some('2') | Some('3') => true,
+            _ => false
+        },
+        None => false
+    }
+}
+
+/// Ask the terminal where its cursor is, with a device status report request. Anything else
+/// read while waiting for the reply is added to `input`.
+pub fn cursor_location(handle: Handle, input: &mut Vec<u8>) -> Option<Location> {
+    write_bytes(handle, b"\x1b[6n");
+
+    // Cursor position report: ESC [ row ; column R, both counted from 1.
+    let reply = read_reply(handle.input, b'R', false, input);
+    let reply = String::from_utf8_lossy(&reply);
+
+    let start = match reply.rfind("\x1b[") {
+        Some(index) => index + 2,
+        None => return None
+    };
+    let mut numbers = reply[start..].trim_right_matches('R').split(';').map(|n| n.parse::<usize>());
+
+    match (numbers.next(), numbers.next()) {
+        (Some(Ok(row)), Some(Ok(column))) if row > 0 && column > 0 => {
+            Some(Location {x: column - 1, y: row - 1})
+        }
+        _ => None
+    }
+}
+
+/// Read the replies to a query, up to the one ending in `last` or until the terminal goes
+/// quiet. Replies are CSI sequences, starting with `ESC [ ?` if `private` is set and ending in
+/// `last` otherwise. Everything else is input that arrived meanwhile, and is added to `input`.
+fn read_reply(fd: c_int, last: u8, private: bool, input: &mut Vec<u8>) -> Vec<u8> {
+    let mut reply = Vec::new();
+    // The sequence being read.
+    let mut sequence: Vec<u8> = Vec::new();
+
+    while wait_readable(fd, QUERY_TIMEOUT_MS) {
+        let byte = match read_byte(fd) {
+            Some(byte) => byte,
+            None => break
+        };
+
+        if sequence.is_empty() && byte != 0x1b {
+            input.push(byte);
+            continue;
+        }
+        sequence.push(byte);
+
+        if sequence.len() == 2 && byte != b'[' {
+            // Not a CSI sequence, but another ESC may start one.
+            let start = if byte == 0x1b { 1 } else { 2 };
+            input.extend(sequence.drain(..start));
+            continue;
+        }
+
+        if sequence.len() > 2 && byte >= 0x40 && byte <= 0x7e {
+            let is_reply = if private { sequence[2] == b'?' } else { byte == last };
+
+            if is_reply {
+                reply.extend(sequence.drain(..));
+                if byte == last {
+                    break;
+                }
+            } else {
+                input.extend(sequence.drain(..));
+            }
+        }
+    }
+
+    // A sequence cut short by the timeout.
+    input.extend(sequence);
+    reply
+}
+
+/// Read as much input as is available, up to the size of `buffer`, without waiting for any.
+/// With `more` set, wait up to the ESC timeout for it, as for the rest of a sequence. Returns
+/// the number of bytes read, or an error once the terminal has gone away.
+pub fn read_available(handle: Handle, buffer: &mut [u8], more: bool) -> io::Result<usize> {
+    if !wait_readable(handle.input, if more { ESC_TIMEOUT_MS } else { 0 }) {
+        return Ok(0);
+    }
+
+    loop {
+        let result = unsafe { read(handle.input, buffer.as_mut_ptr() as *mut c_void, buffer.len() as size_t) };
+
+        if result > 0 {
+            return Ok(result as usize);
+        }
+        if result == 0 {
+            // The other end of the terminal is closed, it keeps reading as readable but empty.
+            return Err(io::Error::from_raw_os_error(EIO));
+        }
+
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(EINTR) => continue,
+            Some(EAGAIN) => return Ok(0),
+            _ => return Err(error)
+        }
+    }
+}
+
+/// Wait up to `timeout` (forever if `None`) for any of `fds` to become readable, and return
+/// the ones that are. Returns nothing on timeout.
+pub fn wait_any(fds: &[c_int], timeout: Option<Duration>) -> Vec<c_int> {
+    let timeout_ms = match timeout {
+        Some(timeout) if timeout > Duration::zero() => timeout.num_milliseconds() as c_int,
+        Some(_) => 0,
+        None => -1
+    };
+    let mut pfds: Vec<pollfd> = fds.iter().map(|&fd| pollfd { fd: fd, events: POLLIN, revents: 0 }).collect();
+
+    loop {
+        let result = unsafe { poll(pfds.as_mut_ptr(), pfds.len() as nfds_t, timeout_ms) };
+
+        if result < 0 && io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+            continue;
+        }
+
+        return pfds.iter().filter(|pfd| pfd.revents != 0).map(|pfd| pfd.fd).collect();
+    }
+}
+
+/// Wait up to `timeout_ms` (forever if negative) for `fd` to become readable.
+fn wait_readable(fd: c_int, timeout_ms: c_int) -> bool {
+    let mut pfd = pollfd { fd: fd, events: POLLIN, revents: 0 };
+
+    loop {
+        let result = unsafe { poll(&mut pfd as *mut pollfd, 1, timeout_ms) };
+
+        if result < 0 && io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+            continue;
+        }
+
+        return result > 0;
+    }
+}
+
+fn read_byte(fd: c_int) -> Option<u8> {
+    let mut byte: u8 = 0;
+
+    loop {
+        let result = unsafe { read(fd, &mut byte as *mut u8 as *mut c_void, 1) };
+
+        if result < 0 && io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+            continue;
+        }
+
+        return if result == 1 { Some(byte) } else { None };
+    }
+}
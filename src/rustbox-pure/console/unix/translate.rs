@@ -0,0 +1,23 @@
+use rustbox::style;
+use rustbox::style::{Color, Style};
+
+/// Build the SGR escape sequence selecting the given colors and style.
+pub fn sgr_sequence(fg: Color, bg: Color, style: Style) -> String {
+    let mut sequence = String::from("\x1b[0");
+
+    if style.contains(style::RB_BOLD) { sequence.push_str(";1"); }
+    if style.contains(style::RB_UNDERLINE) { sequence.push_str(";4"); }
+    if style.contains(style::RB_REVERSE) { sequence.push_str(";7"); }
+
+    match fg {
+        Color::Default => sequence.push_str(";39"),
+        fg => sequence.push_str(&format!(";{}", 30 + fg as u16 - 1))
+    }
+    match bg {
+        Color::Default => sequence.push_str(";49"),
+        bg => sequence.push_str(&format!(";{}", 40 + bg as u16 - 1))
+    }
+
+    sequence.push('m');
+    sequence
+}
@@ -1,4 +1,5 @@
 use std::mem;
+use time::Duration;
 
 use rustbox::console::{Handle, Size, Location, RawEvent};
 use rustbox::console::api::*;
@@ -21,6 +22,15 @@ pub fn create_buffer() -> HANDLE {
     handle
 }
 
+/// Have `handler` called, on a thread of its own, for console control events such as Ctrl-c.
+pub fn add_ctrl_handler(handler: PHANDLER_ROUTINE) {
+    unsafe { SetConsoleCtrlHandler(handler, 1); }
+}
+
+pub fn remove_ctrl_handler(handler: PHANDLER_ROUTINE) {
+    unsafe { SetConsoleCtrlHandler(handler, 0); }
+}
+
 pub fn set_buffer(buffer: HANDLE) {
     unsafe { SetConsoleActiveScreenBuffer(buffer); }
 }
@@ -41,6 +51,30 @@ pub fn stdout_buffer() -> HANDLE {
     unsafe { GetStdHandle(STD_OUTPUT_HANDLE) }
 }
 
+/// The console's input buffer, even when stdin is redirected.
+pub fn console_input() -> HANDLE {
+    open_console(b"CONIN$\0")
+}
+
+/// The console's active output buffer, even when stdout is redirected.
+pub fn console_output() -> HANDLE {
+    open_console(b"CONOUT$\0")
+}
+
+fn open_console(name: &[u8]) -> HANDLE {
+    unsafe {
+        CreateFileA(
+            name.as_ptr() as LPCSTR,
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            0 as *mut SECURITY_ATTRIBUTES,
+            OPEN_EXISTING,
+            0 as DWORD,
+            0 as HANDLE
+        )
+    }
+}
+
 pub fn set_mode(handle: Handle, enable_mouse: bool, enable_ctrlc: bool) {
     let mut mode: DWORD = 0;
     if enable_mouse { mode = mode | ENABLE_MOUSE_INPUT; }
@@ -199,6 +233,12 @@ pub fn read_input(handle: Handle) -> RawEvent {
     RawEvent { record: record }
 }
 
+pub fn read_input_timeout(handle: Handle, timeout: Duration) -> Option<RawEvent> {
+    let result = unsafe { WaitForSingleObject(handle.input, timeout.num_milliseconds() as DWORD) };
+
+    if result == WAIT_OBJECT_0 { Some(read_input(handle)) } else { None }
+}
+
 pub fn set_scroll_enable(handle: Handle, enable: bool) {
     unsafe {
         EnableScrollBar(
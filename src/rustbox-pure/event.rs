@@ -2,12 +2,39 @@ use std::error::Error;
 use std::fmt;
 use num::FromPrimitive;
 
-#[derive(Clone, Copy)]
+use super::sender::Payload;
+use super::timer::TimerId;
+
+#[derive(Clone, Debug)]
 pub enum Event {
+    /// A key press as termbox reported it, with its Alt flag, key code and character, returned
+    /// instead of KeyEvent when raw events are asked for. See the keyboard module.
     KeyEventRaw(u8, u16, u32),
-    KeyEvent(Option<Key>),
+    /// A key press, with the modifiers held down. Ctrl with a letter is reported as
+    /// `Key::Ctrl`, with MOD_CTRL set as well.
+    KeyEvent(Option<Key>, Modifiers),
+    /// A key held down long enough to repeat. Only reported with InitOptions::kitty_keyboard;
+    /// otherwise repeats are reported as KeyEvent.
+    KeyRepeat(Option<Key>, Modifiers),
+    /// A key let go. Only reported with InitOptions::kitty_keyboard.
+    KeyRelease(Option<Key>, Modifiers),
     ResizeEvent(i32, i32),
     MouseEvent(Mouse, i32, i32),
+    /// Text pasted into the terminal, all at once, see InitOptions::bracketed_paste. Without
+    /// it, pasted text arrives as KeyEvents like typed text.
+    Paste(String),
+    /// The process was continued after being stopped, for instance with Ctrl-z. The terminal
+    /// has been set up again and the last frame redrawn.
+    ResumeEvent,
+    /// The process received a signal, see InitOptions::signal_events. The terminal is left as
+    /// it is, it is given back when RustBox is dropped.
+    Signal(Signal),
+    /// A payload sent with an EventSender.
+    User(Payload),
+    /// A descriptor registered with RustBox::register_fd, given by its token, is readable.
+    Fd(usize),
+    /// A timer started with RustBox::start_timer or start_repeating_timer expired.
+    Timer(TimerId),
     NoEvent
 }
 
@@ -49,6 +76,55 @@ impl FromPrimitive for EventError {
    }
 }
 
+bitflags! {
+    flags Modifiers: u8 {
+        const MOD_ALT = 0x01,
+        const MOD_CTRL = 0x02,
+        const MOD_SHIFT = 0x04,
+        const MOD_SUPER = 0x08,
+        const MOD_NONE = 0x00,
+    }
+}
+
+impl Modifiers {
+    /// Decode the modifier parameter of xterm's key sequences, such as the 5 in `ESC [ 1 ; 5 A`
+    /// for Ctrl-Up. It is one more than a mask of Shift (1), Alt (2), Ctrl (4) and Meta (8),
+    /// which is taken to be Super.
+    pub fn from_xterm(param: u32) -> Modifiers {
+        let mask = param.saturating_sub(1);
+        let mut modifiers = MOD_NONE;
+
+        if mask & 1 != 0 { modifiers.insert(MOD_SHIFT); }
+        if mask & 2 != 0 { modifiers.insert(MOD_ALT); }
+        if mask & 4 != 0 { modifiers.insert(MOD_CTRL); }
+        if mask & 8 != 0 { modifiers.insert(MOD_SUPER); }
+
+        modifiers
+    }
+
+    /// MOD_CTRL for `Key::Ctrl`, which implies it, and nothing for other keys.
+    pub fn implied_by(key: Option<Key>) -> Modifiers {
+        match key {
+            Some(Key::Ctrl(_)) => MOD_CTRL,
+            _ => MOD_NONE
+        }
+    }
+}
+
+/// Signals reported by Event::Signal.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Signal {
+    /// SIGINT, or Ctrl-c/Ctrl-Break on Windows.
+    Interrupt,
+    /// SIGTERM, or the user logging off or shutting down on Windows.
+    Terminate,
+    /// SIGHUP, or the console window being closed on Windows. The terminal has usually gone
+    /// away, but is still drawn on and restored in case it has not.
+    Hangup,
+    /// SIGCONT, reported instead of ResumeEvent. Not reported on Windows.
+    Continue
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Mouse {
     Left,
@@ -70,6 +146,7 @@ pub enum Key {
     Left,
     Up,
     Down,
+    Insert,
     Delete,
 
     Home,
@@ -0,0 +1,122 @@
+//! A backend without a terminal, for testing RustBox applications.
+//!
+//! `present()` renders into an in-memory CellBuffer that can be inspected afterwards, and
+//! `poll_event`/`peek_event` return events from a scripted queue.
+//!
+//! ```
+//! use std::default::Default;
+//! use rustbox::{RustBox, Headless, Event, Key, Color, Cell};
+//!
+//! let mut headless = Headless::new(20, 5);
+//! headless.push_event(Event::KeyEvent(Some(Key::Char('q')), rustbox::MOD_NONE));
+//!
+//! let rb = RustBox::with_backend(headless, Default::default()).unwrap();
+//! rb.print(1, 1, rustbox::RB_BOLD, Color::White, Color::Blue, "Hi");
+//! rb.present();
+//!
+//! assert_eq!(rb.backend().screen().get(1, 1),
+//!            Some(Cell { ch: 'H', fg: Color::White, bg: Color::Blue, sty: rustbox::RB_BOLD }));
+//!
+//! match rb.poll_event(false) {
+//!     Ok(Event::KeyEvent(Some(Key::Char('q')), _)) => {}
+//!     _ => panic!("expected the scripted key")
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use time::Duration;
+
+use super::{InitError, InitOptions};
+use super::backend::{Backend, Size, Location};
+use super::cell::{Cell, CellBuffer};
+use super::event::{Event, EventResult};
+
+pub struct Headless {
+    screen: CellBuffer,
+    cursor: Option<Location>,
+    events: VecDeque<Event>,
+    frames: usize
+}
+
+impl Headless {
+    pub fn new(width: usize, height: usize) -> Headless {
+        Headless {
+            screen: CellBuffer::new(width, height, Default::default()),
+            cursor: None,
+            events: VecDeque::new(),
+            frames: 0
+        }
+    }
+
+    /// Queue an event to be returned by `poll_event`/`peek_event`.
+    ///
+    /// Reading a `ResizeEvent` also resizes the screen, so the next `present()` is drawn at
+    /// the new size.
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Everything that has been presented so far.
+    pub fn screen(&self) -> &CellBuffer {
+        &self.screen
+    }
+
+    /// Where the cursor is shown, or `None` if it is hidden.
+    pub fn cursor(&self) -> Option<Location> {
+        self.cursor
+    }
+
+    /// Number of times `present()` has completed.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+
+impl Backend for Headless {
+    fn begin_display(&mut self, _opts: &InitOptions) -> Result<(), InitError> {
+        Ok(())
+    }
+
+    fn finish_display(&mut self) {}
+
+    fn size(&self) -> Size {
+        Size {width: self.screen.width, height: self.screen.height}
+    }
+
+    fn write_cells(&mut self, cells: &[Cell], origin: Location) {
+        if origin.y >= self.screen.height {
+            return;
+        }
+
+        let width = self.screen.width;
+        for (i, cell) in cells.iter().enumerate().take(width.saturating_sub(origin.x)) {
+            self.screen.cells[(origin.y * width) + origin.x + i] = *cell;
+        }
+    }
+
+    fn flush(&mut self) {
+        self.frames += 1;
+    }
+
+    fn set_cursor(&mut self, location: Option<Location>) {
+        self.cursor = location;
+    }
+
+    /// Scripted events are returned immediately regardless of `timeout`. Once the queue is
+    /// empty this returns `Event::NoEvent` rather than blocking forever.
+    fn read_input(&mut self, _timeout: Option<Duration>, _raw: bool) -> EventResult {
+        match self.events.pop_front() {
+            Some(Event::ResizeEvent(width, height)) => {
+                self.screen.resize(width as usize, height as usize, Default::default());
+                Ok(Event::ResizeEvent(width, height))
+            }
+            Some(event) => Ok(event),
+            None => Ok(Event::NoEvent)
+        }
+    }
+
+    /// Several headless RustBoxes may run at once, for instance in parallel tests.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
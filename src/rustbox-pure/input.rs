@@ -0,0 +1,723 @@
+//! Decoding of the bytes a terminal sends into events, independent of any backend.
+//!
+//! Handles UTF-8 text, control keys, CSI and SS3 sequences with xterm's modifier parameters,
+//! Alt as an ESC prefix (see `Decoder::set_alt`), X10 and SGR mouse reports, and the kitty
+//! keyboard protocol's key events, including repeats and releases. Text pasted between
+//! bracketed paste markers comes as a single `Event::Paste`.
+//!
+//! ```
+//! use rustbox::input::Decoder;
+//! use rustbox::{Event, Key};
+//!
+//! let mut decoder = Decoder::new();
+//!
+//! // Ctrl-Up, split over two reads.
+//! assert!(decoder.feed(b"\x1b[1;").is_empty());
+//! match decoder.feed(b"5A").first() {
+//!     Some(&Event::KeyEvent(Some(Key::Up), modifiers)) => assert_eq!(modifiers, rustbox::MOD_CTRL),
+//!     _ => panic!("expected Ctrl-Up")
+//! }
+//!
+//! // A paste, newline and all.
+//! match decoder.feed(b"\x1b[200~one\rtwo\x1b[201~").first() {
+//!     Some(&Event::Paste(ref text)) => assert_eq!(text, "one\rtwo"),
+//!     _ => panic!("expected a paste")
+//! }
+//! ```
+
+use std::ascii::AsciiExt;
+use std::char;
+use std::cmp;
+use std::str;
+
+use super::event::{Event, Key, Mouse, Modifiers, MOD_ALT, MOD_CTRL, MOD_SHIFT, MOD_NONE};
+
+const ESC: u8 = 0x1b;
+
+// Bracketed paste markers, sent around pasted text once enabled with `ESC [ ? 2004 h`.
+const PASTE_START: &'static [u8] = b"\x1b[200~";
+const PASTE_END: &'static [u8] = b"\x1b[201~";
+
+// Most bytes of a paste collected before they are reported, so that a paste of any size, or
+// one whose end marker was lost, takes up a bounded amount of memory. The rest is reported as
+// further pastes.
+const MAX_PASTE: usize = 1 << 20;
+
+/// Turns bytes read from a terminal into events. Sequences may be split over any number of
+/// calls to `feed`.
+pub struct Decoder {
+    // Bytes of a sequence that has not been completed yet.
+    pending: Vec<u8>,
+    // Text pasted so far, while between the paste markers.
+    paste: Option<Vec<u8>>,
+    // Whether ESC before a key is Alt, rather than the Esc key.
+    alt: bool
+}
+
+enum Parse {
+    // Bytes taken, and the event they stand for, if any.
+    Complete(usize, Option<Event>),
+    // More bytes are needed.
+    Incomplete
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder { pending: Vec::new(), paste: None, alt: false }
+    }
+
+    /// Whether ESC followed by a key that starts no sequence is that key with Alt, as in
+    /// InputMode::Alt, rather than Esc and then the key, as in InputMode::Esc (the default).
+    pub fn set_alt(&mut self, alt: bool) {
+        self.alt = alt;
+    }
+
+    /// Decode `bytes`, following any left over from the last call. Bytes that may be the start
+    /// of a sequence are kept until it is complete.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        self.pending.extend(bytes.iter().cloned());
+        self.decode(false)
+    }
+
+    /// Decode whatever is left over as it is, for when no more bytes have arrived in time to
+    /// complete a sequence. A lone ESC is the Esc key. A paste ends with what has arrived of
+    /// it, as its end marker is not coming anymore, so callers should allow a paste longer to
+    /// complete than a sequence (see `is_pasting`).
+    pub fn flush(&mut self) -> Vec<Event> {
+        self.decode(true)
+    }
+
+    /// Whether bytes are being kept for an incomplete sequence, so that `flush` should be
+    /// called if no more arrive soon.
+    pub fn is_pending(&self) -> bool {
+        self.paste.is_none() && !self.pending.is_empty()
+    }
+
+    /// Whether a paste has started but not ended yet.
+    pub fn is_pasting(&self) -> bool {
+        self.paste.is_some()
+    }
+
+    fn decode(&mut self, flush: bool) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut start = 0;
+
+        while start < self.pending.len() {
+            if self.paste.is_some() {
+                match find(&self.pending[start..], PASTE_END) {
+                    Some(end) => {
+                        let mut text = self.paste.take().unwrap_or_else(Vec::new);
+                        text.extend(self.pending[start..start + end].iter().cloned());
+                        events.push(Event::Paste(String::from_utf8_lossy(&text).into_owned()));
+                        start += end + PASTE_END.len();
+                        continue;
+                    }
+                    None => {
+                        // Keep back what may be the start of the end marker, unless it is not
+                        // coming.
+                        let keep = if flush { 0 } else { partial_suffix(&self.pending[start..], PASTE_END) };
+                        let end = self.pending.len() - keep;
+                        if let Some(ref mut text) = self.paste {
+                            text.extend(self.pending[start..end].iter().cloned());
+                        }
+                        start = end;
+                        break;
+                    }
+                }
+            }
+
+            if self.pending[start..].starts_with(PASTE_START) {
+                self.paste = Some(Vec::new());
+                start += PASTE_START.len();
+                continue;
+            }
+
+            match parse(&self.pending[start..], self.alt) {
+                Parse::Complete(len, event) => {
+                    events.extend(event);
+                    start += len;
+                }
+                Parse::Incomplete if flush => {
+                    // An ESC that did not start a sequence after all was the key itself, and
+                    // the rest is decoded on its own. A partial UTF-8 character is dropped.
+                    if self.pending[start] == ESC {
+                        events.push(key_event(Some(Key::Esc), MOD_NONE));
+                        start += 1;
+                    } else {
+                        start = self.pending.len();
+                    }
+                }
+                Parse::Incomplete => break
+            }
+        }
+
+        if let Some(mut text) = self.paste.take() {
+            if flush {
+                events.push(Event::Paste(String::from_utf8_lossy(&text).into_owned()));
+            } else {
+                if text.len() >= MAX_PASTE {
+                    // Report what there is so far, short of a character split at its end.
+                    let rest = text.split_off(char_boundary(&text));
+                    events.push(Event::Paste(String::from_utf8_lossy(&text).into_owned()));
+                    text = rest;
+                }
+                self.paste = Some(text);
+            }
+        }
+
+        self.pending.drain(..start);
+        events
+    }
+}
+
+/// Where `needle` first occurs in `bytes`.
+fn find(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    if bytes.len() < needle.len() {
+        return None;
+    }
+    (0..bytes.len() - needle.len() + 1).find(|&index| bytes[index..].starts_with(needle))
+}
+
+/// Length of the longest end of `bytes` that `needle` starts with.
+fn partial_suffix(bytes: &[u8], needle: &[u8]) -> usize {
+    (1..cmp::min(bytes.len(), needle.len() - 1) + 1).rev()
+        .find(|&len| needle.starts_with(&bytes[bytes.len() - len..]))
+        .unwrap_or(0)
+}
+
+/// The length of `bytes` short of a UTF-8 character split at its end.
+fn char_boundary(bytes: &[u8]) -> usize {
+    // The last byte that starts a character, if within a character's length of the end.
+    let lead = (bytes.len().saturating_sub(4)..bytes.len()).rev()
+        .find(|&index| bytes[index] & 0xc0 != 0x80);
+
+    match lead {
+        Some(index) if index + utf8_length(bytes[index]).unwrap_or(1) > bytes.len() => index,
+        _ => bytes.len()
+    }
+}
+
+/// `alt` tells whether ESC before a key is Alt, see `Decoder::set_alt`.
+fn parse(bytes: &[u8], alt: bool) -> Parse {
+    if bytes[0] != ESC {
+        return parse_text(bytes);
+    }
+    if bytes.len() == 1 {
+        return Parse::Incomplete;
+    }
+
+    match bytes[1] {
+        b'[' => parse_csi(bytes),
+        b'O' => match bytes.get(2) {
+            Some(&final_byte) => Parse::Complete(3, translate_ss3(final_byte).map(|key| key_event(Some(key), MOD_NONE))),
+            None => Parse::Incomplete
+        },
+        _ if !alt => Parse::Complete(1, Some(key_event(Some(Key::Esc), MOD_NONE))),
+        // Alt+key arrives as ESC followed by the key.
+        _ => match parse(&bytes[1..], alt) {
+            Parse::Complete(len, Some(Event::KeyEvent(key, modifiers))) => {
+                Parse::Complete(len + 1, Some(key_event(key, modifiers | MOD_ALT)))
+            }
+            Parse::Complete(len, event) => Parse::Complete(len + 1, event),
+            Parse::Incomplete => Parse::Incomplete
+        }
+    }
+}
+
+/// The length of the UTF-8 character starting with `lead`, or None if it starts none.
+fn utf8_length(lead: u8) -> Option<usize> {
+    match lead {
+        0x00 ... 0x7f => Some(1),
+        0xc0 ... 0xdf => Some(2),
+        0xe0 ... 0xef => Some(3),
+        0xf0 ... 0xf7 => Some(4),
+        _ => None
+    }
+}
+
+/// A character or control key.
+fn parse_text(bytes: &[u8]) -> Parse {
+    let len = match utf8_length(bytes[0]) {
+        Some(len) => len,
+        // Not the start of a character.
+        None => return Parse::Complete(1, None)
+    };
+
+    if bytes.len() < len {
+        return Parse::Incomplete;
+    }
+
+    if len > 1 {
+        return match str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next()) {
+            Some(c) => Parse::Complete(len, Some(key_event(Some(Key::Char(c)), MOD_NONE))),
+            None => Parse::Complete(1, None)
+        };
+    }
+
+    let key = match bytes[0] {
+        0x09 => Some(Key::Tab),
+        0x0d => Some(Key::Enter),
+        0x1f => Some(Key::Ctrl('/')),
+        0x7f => Some(Key::Backspace),
+        c @ 0x01 ... 0x1a => Some(Key::Ctrl((c + b'a' - 1) as char)),
+        c @ 0x20 ... 0x7e => Some(Key::Char(c as char)),
+        _ => None
+    };
+
+    Parse::Complete(1, key.map(|key| key_event(Some(key), MOD_NONE)))
+}
+
+/// A CSI sequence, starting with `ESC [`.
+fn parse_csi(bytes: &[u8]) -> Parse {
+    match bytes.get(2) {
+        // Legacy X10 mouse report, three bytes after the M.
+        Some(&b'M') => {
+            return if bytes.len() < 6 {
+                Parse::Incomplete
+            } else {
+                Parse::Complete(6, translate_x10_mouse(bytes[3], bytes[4], bytes[5]))
+            };
+        }
+        // Linux console F1 to F5, `ESC [ [ A` to `ESC [ [ E`.
+        Some(&b'[') => {
+            return match bytes.get(3) {
+                Some(&final_byte) => Parse::Complete(4, translate_linux_function(final_byte).map(|key| key_event(Some(key), MOD_NONE))),
+                None => Parse::Incomplete
+            };
+        }
+        _ => {}
+    }
+
+    // Parameter and intermediate bytes, up to the final byte.
+    for (index, &byte) in bytes.iter().enumerate().skip(2) {
+        match byte {
+            0x20 ... 0x3f => continue,
+            0x40 ... 0x7e => return Parse::Complete(index + 1, translate_csi(&bytes[2..index], byte)),
+            // Not a valid sequence, drop what there is of it.
+            _ => return Parse::Complete(index, None)
+        }
+    }
+
+    Parse::Incomplete
+}
+
+/// A complete CSI sequence, given its parameters and final byte. Modified keys have the
+/// modifiers as their second parameter, as in `ESC [ 1 ; 5 A` or `ESC [ 3 ; 2 ~`. The kitty
+/// keyboard protocol adds the kind of event after a colon, as in `ESC [ 1 ; 5 : 3 A` for the
+/// release of Ctrl-Up, and reports other keys as `ESC [ code ; modifiers u`.
+fn translate_csi(params: &[u8], final_byte: u8) -> Option<Event> {
+    if params.starts_with(b"<") {
+        return match final_byte {
+            b'M' | b'm' => translate_sgr_mouse(&params[1..], final_byte),
+            _ => None
+        };
+    }
+
+    let fields = parse_fields(params);
+    let field = |index: usize, sub: usize| fields.get(index).and_then(|field| field.get(sub)).cloned();
+
+    let modifiers = match field(1, 0) {
+        Some(param) => Modifiers::from_xterm(param),
+        None => MOD_NONE
+    };
+    let kind = field(1, 1).unwrap_or(1);
+
+    let key = match final_byte {
+        b'u' => match field(0, 0).and_then(|code| translate_kitty(code, field(0, 1), modifiers)) {
+            Some(key) => key,
+            None => return None
+        },
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        // A cursor position report, `ESC [ row ; column R`, that arrived after the query gave
+        // up waiting for it. F3 with modifiers is `ESC [ 1 ; modifiers R`, so only reports
+        // for the first few columns of the first row look the same.
+        b'R' if field(0, 0).unwrap_or(0) != 1 || field(1, 0).unwrap_or(0) > 16 => return None,
+        // F1 to F4 when modified, as ESC O does not take parameters.
+        b'P' ... b'S' => Key::F((final_byte - b'P' + 1) as u32),
+        // Shift-Tab.
+        b'Z' => return Some(key_event(Some(Key::Tab), modifiers | MOD_SHIFT)),
+        b'~' => match field(0, 0).and_then(translate_tilde) {
+            Some(key) => key,
+            None => return None
+        },
+        _ => return None
+    };
+
+    let modifiers = modifiers | Modifiers::implied_by(Some(key));
+    match kind {
+        2 => Some(Event::KeyRepeat(Some(key), modifiers)),
+        3 => Some(Event::KeyRelease(Some(key), modifiers)),
+        _ => Some(Event::KeyEvent(Some(key), modifiers))
+    }
+}
+
+/// The key of `ESC [ code ; modifiers u`, given the code of the key with Shift held as well
+/// if the terminal reported it. Keys such as Tab and Ctrl-i are told apart here, as they are
+/// not in the legacy encoding.
+fn translate_kitty(code: u32, shifted: Option<u32>, modifiers: Modifiers) -> Option<Key> {
+    match code {
+        9 => Some(Key::Tab),
+        13 => Some(Key::Enter),
+        27 => Some(Key::Esc),
+        127 => Some(Key::Backspace),
+        // F13 to F35.
+        57376 ... 57398 => Some(Key::F(code - 57376 + 13)),
+        // Keypad keys, reported as the keys they stand for.
+        57399 ... 57408 => char::from_u32('0' as u32 + code - 57399).map(Key::Char),
+        57409 => Some(Key::Char('.')),
+        57410 => Some(Key::Char('/')),
+        57411 => Some(Key::Char('*')),
+        57412 => Some(Key::Char('-')),
+        57413 => Some(Key::Char('+')),
+        57414 => Some(Key::Enter),
+        57415 => Some(Key::Char('=')),
+        57416 => Some(Key::Char(',')),
+        57417 => Some(Key::Left),
+        57418 => Some(Key::Right),
+        57419 => Some(Key::Up),
+        57420 => Some(Key::Down),
+        57421 => Some(Key::PageUp),
+        57422 => Some(Key::PageDown),
+        57423 => Some(Key::Home),
+        57424 => Some(Key::End),
+        57425 => Some(Key::Insert),
+        57426 => Some(Key::Delete),
+        // Other keys in the private use area are media and modifier keys, which are not
+        // reported.
+        57344 ... 63743 => None,
+        _ => {
+            let code = if modifiers.contains(MOD_SHIFT) { shifted.unwrap_or(code) } else { code };
+
+            match char::from_u32(code) {
+                Some(c @ 'a' ... 'z') | Some(c @ 'A' ... 'Z') if modifiers.contains(MOD_CTRL) => {
+                    Some(Key::Ctrl(c.to_ascii_lowercase()))
+                }
+                Some(c) => Some(Key::Char(c)),
+                None => None
+            }
+        }
+    }
+}
+
+/// The key of `ESC [ code ~`.
+fn translate_tilde(code: u32) -> Option<Key> {
+    match code {
+        1 | 7 => Some(Key::Home),
+        2 => Some(Key::Insert),
+        3 => Some(Key::Delete),
+        4 | 8 => Some(Key::End),
+        5 => Some(Key::PageUp),
+        6 => Some(Key::PageDown),
+        11 ... 15 => Some(Key::F(code - 10)),
+        17 ... 21 => Some(Key::F(code - 11)),
+        23 ... 26 => Some(Key::F(code - 12)),
+        28 | 29 => Some(Key::F(code - 13)),
+        31 ... 34 => Some(Key::F(code - 14)),
+        _ => None
+    }
+}
+
+fn translate_ss3(final_byte: u8) -> Option<Key> {
+    match final_byte {
+        b'A' => Some(Key::Up),
+        b'B' => Some(Key::Down),
+        b'C' => Some(Key::Right),
+        b'D' => Some(Key::Left),
+        b'H' => Some(Key::Home),
+        b'F' => Some(Key::End),
+        b'P' ... b'S' => Some(Key::F((final_byte - b'P' + 1) as u32)),
+        _ => None
+    }
+}
+
+fn translate_linux_function(final_byte: u8) -> Option<Key> {
+    match final_byte {
+        b'A' ... b'E' => Some(Key::F((final_byte - b'A' + 1) as u32)),
+        _ => None
+    }
+}
+
+/// SGR (1006) mouse report: `ESC [ < button ; x ; y M` for press, `m` for release.
+fn translate_sgr_mouse(params: &[u8], final_byte: u8) -> Option<Event> {
+    let params = parse_params(params);
+    if params.len() < 3 {
+        return None;
+    }
+
+    let (button, x, y) = (params[0], params[1] as i32 - 1, params[2] as i32 - 1);
+
+    let mouse = if final_byte == b'm' {
+        Mouse::Release
+    }
+    else if button & 32 != 0 {
+        Mouse::Move
+    }
+    else {
+        match button & 0x43 {
+            0 => Mouse::Left,
+            1 => Mouse::Middle,
+            2 => Mouse::Right,
+            64 => Mouse::WheelUp,
+            65 => Mouse::WheelDown,
+            _ => return None
+        }
+    };
+
+    Some(Event::MouseEvent(mouse, x, y))
+}
+
+/// Legacy X10 mouse report, with each value offset by 32.
+fn translate_x10_mouse(button: u8, x: u8, y: u8) -> Option<Event> {
+    let (x, y) = (x as i32 - 33, y as i32 - 33);
+    let button = button.wrapping_sub(32);
+
+    let mouse = match button & 0x43 {
+        0 => Mouse::Left,
+        1 => Mouse::Middle,
+        2 => Mouse::Right,
+        3 => Mouse::Release,
+        64 => Mouse::WheelUp,
+        65 => Mouse::WheelDown,
+        _ => return None
+    };
+
+    Some(Event::MouseEvent(mouse, x, y))
+}
+
+/// Parameters separated by semicolons, each of them subparameters separated by colons.
+fn parse_fields(params: &[u8]) -> Vec<Vec<u32>> {
+    str::from_utf8(params).unwrap_or("")
+        .split(';')
+        .map(|field| field.split(':').map(|sub| sub.parse().unwrap_or(0)).collect())
+        .collect()
+}
+
+fn parse_params(params: &[u8]) -> Vec<u32> {
+    str::from_utf8(params).unwrap_or("")
+        .split(';')
+        .map(|param| param.parse().unwrap_or(0))
+        .collect()
+}
+
+fn key_event(key: Option<Key>, modifiers: Modifiers) -> Event {
+    Event::KeyEvent(key, modifiers | Modifiers::implied_by(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decoder;
+    use super::super::event::{Event, Key, Mouse, MOD_ALT, MOD_CTRL, MOD_NONE};
+
+    fn decode(bytes: &[u8]) -> Vec<Event> {
+        let mut decoder = Decoder::new();
+        let mut events = decoder.feed(bytes);
+        events.extend(decoder.flush());
+        events
+    }
+
+    fn key(event: &Event) -> Option<(Key, u8)> {
+        match *event {
+            Event::KeyEvent(Some(key), modifiers) => Some((key, modifiers.bits())),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn sequence_split_over_reads() {
+        let mut decoder = Decoder::new();
+
+        assert!(decoder.feed(b"\x1b").is_empty());
+        assert!(decoder.feed(b"[1;").is_empty());
+        assert!(decoder.is_pending());
+
+        let events = decoder.feed(b"5A");
+        assert_eq!(events.len(), 1);
+        assert_eq!(key(&events[0]), Some((Key::Up, MOD_CTRL.bits())));
+        assert!(!decoder.is_pending());
+    }
+
+    #[test]
+    fn several_events_in_one_read() {
+        let events = decode(b"ab\x1b[A\r");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Char('a'), 0)), Some((Key::Char('b'), 0)),
+                              Some((Key::Up, 0)), Some((Key::Enter, 0))]);
+    }
+
+    #[test]
+    fn lone_esc_on_flush() {
+        let mut decoder = Decoder::new();
+
+        assert!(decoder.feed(b"\x1b").is_empty());
+        let events = decoder.flush();
+        assert_eq!(events.len(), 1);
+        assert_eq!(key(&events[0]), Some((Key::Esc, 0)));
+        assert!(!decoder.is_pending());
+    }
+
+    #[test]
+    fn esc_esc_on_flush() {
+        let events = decode(b"\x1b\x1b");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Esc, 0)), Some((Key::Esc, 0))]);
+    }
+
+    #[test]
+    fn alt_prefixed_sequence() {
+        let mut decoder = Decoder::new();
+        decoder.set_alt(true);
+
+        let events = decoder.feed(b"\x1bx\x1b\x1b[A");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Char('x'), MOD_ALT.bits())), Some((Key::Up, MOD_ALT.bits()))]);
+    }
+
+    #[test]
+    fn esc_before_key() {
+        let events = decode(b"\x1bx\x1b\x1b[A");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Esc, 0)), Some((Key::Char('x'), 0)), Some((Key::Esc, 0)),
+                              Some((Key::Up, 0))]);
+    }
+
+    #[test]
+    fn long_sequence() {
+        // Longer than the fixed size buffers sequences were once read into.
+        let events = decode(b"\x1b[?64;1;2;6;9;15;16;17;18;21;22;28;29c");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn utf8() {
+        let mut decoder = Decoder::new();
+
+        assert!(decoder.feed(b"\xc3").is_empty());
+        let events = decoder.feed(b"\xa9\xe2\x82\xac");
+        let keys: Vec<_> = events.iter().map(key).collect();
+        assert_eq!(keys, vec![Some((Key::Char('é'), 0)), Some((Key::Char('€'), 0))]);
+
+        // A partial character is dropped.
+        assert!(decoder.feed(b"\xe2\x82").is_empty());
+        assert!(decoder.flush().is_empty());
+    }
+
+    #[test]
+    fn control_keys() {
+        let events = decode(b"\x03\x09\x7f");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Ctrl('c'), MOD_CTRL.bits())), Some((Key::Tab, 0)),
+                              Some((Key::Backspace, 0))]);
+    }
+
+    #[test]
+    fn sgr_mouse() {
+        let events = decode(b"\x1b[<0;10;5M\x1b[<0;10;5m\x1b[<64;1;1M");
+
+        match (&events[0], &events[1], &events[2]) {
+            (&Event::MouseEvent(Mouse::Left, 9, 4), &Event::MouseEvent(Mouse::Release, 9, 4),
+             &Event::MouseEvent(Mouse::WheelUp, 0, 0)) => {}
+            _ => panic!("unexpected {:?}", events)
+        }
+    }
+
+    #[test]
+    fn x10_mouse() {
+        let events = decode(b"\x1b[M\x20\x2a\x25");
+
+        match events.first() {
+            Some(&Event::MouseEvent(Mouse::Left, 9, 4)) => {}
+            _ => panic!("unexpected {:?}", events)
+        }
+    }
+
+    #[test]
+    fn paste() {
+        let mut decoder = Decoder::new();
+
+        assert!(decoder.feed(b"\x1b[200~one\r").is_empty());
+        assert!(decoder.is_pasting());
+        // The end marker may be split too.
+        assert!(decoder.feed(b"two\x1b[20").is_empty());
+        assert!(!decoder.is_pending());
+
+        let events = decoder.feed(b"1~x");
+        match (events.get(0), events.get(1).and_then(key)) {
+            (Some(&Event::Paste(ref text)), Some((Key::Char('x'), 0))) => assert_eq!(text, "one\rtwo"),
+            _ => panic!("unexpected {:?}", events)
+        }
+        assert!(!decoder.is_pasting());
+    }
+
+    #[test]
+    fn paste_without_end_marker() {
+        let mut decoder = Decoder::new();
+
+        assert!(decoder.feed(b"\x1b[200~one\x1b[2").is_empty());
+
+        // Given up on, the paste is what arrived of it, and what follows is decoded as usual.
+        let events = decoder.flush();
+        match events.get(0) {
+            Some(&Event::Paste(ref text)) => assert_eq!(text, "one\x1b[2"),
+            _ => panic!("unexpected {:?}", events)
+        }
+        assert!(!decoder.is_pasting());
+        assert_eq!(decoder.feed(b"x").iter().map(key).collect::<Vec<_>>(), vec![Some((Key::Char('x'), 0))]);
+    }
+
+    #[test]
+    fn long_paste() {
+        let mut decoder = Decoder::new();
+        let mut bytes = b"\x1b[200~".to_vec();
+        bytes.extend(vec![b'a'; super::MAX_PASTE - 1]);
+        // A character split over the limit.
+        bytes.extend("\u{e9}".as_bytes()[..1].iter().cloned());
+
+        let events = decoder.feed(&bytes);
+        match events.get(0) {
+            Some(&Event::Paste(ref text)) => assert_eq!(text.len(), super::MAX_PASTE - 1),
+            _ => panic!("expected a paste")
+        }
+        assert!(decoder.is_pasting());
+
+        let events = decoder.feed(&"\u{e9}\x1b[201~".as_bytes()[1..]);
+        match events.get(0) {
+            Some(&Event::Paste(ref text)) => assert_eq!(text, "\u{e9}"),
+            _ => panic!("expected a paste")
+        }
+    }
+
+    #[test]
+    fn cursor_position_report() {
+        assert!(decode(b"\x1b[12;40R").is_empty());
+
+        let events = decode(b"\x1b[1;5R");
+        assert_eq!(events.iter().map(key).collect::<Vec<_>>(), vec![Some((Key::F(3), MOD_CTRL.bits()))]);
+    }
+
+    #[test]
+    fn kitty_keypad() {
+        let events = decode(b"\x1b[57399u\x1b[57413u\x1b[57414u\x1b[57417;5u\x1b[57441u");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Char('0'), 0)), Some((Key::Char('+'), 0)),
+                              Some((Key::Enter, 0)), Some((Key::Left, MOD_CTRL.bits()))]);
+    }
+
+    #[test]
+    fn unknown_sequence() {
+        let events = decode(b"\x1b[99zq");
+        let keys: Vec<_> = events.iter().map(key).collect();
+
+        assert_eq!(keys, vec![Some((Key::Char('q'), MOD_NONE.bits()))]);
+    }
+}
@@ -0,0 +1,53 @@
+//! Termbox's key codes, for backends reporting Event::KeyEventRaw as termbox did.
+
+use super::event::{Event, Key, Modifiers, MOD_ALT};
+
+/// Set in the first field of Event::KeyEventRaw for keys pressed with Alt.
+pub const RAW_MOD_ALT: u8 = 0x01;
+
+impl Key {
+    /// Termbox's key code and character for this key, as in Event::KeyEventRaw: characters
+    /// have code 0, other keys no character. None for keys termbox has no code for.
+    pub fn to_raw(&self) -> Option<(u16, u32)> {
+        let code = match *self {
+            Key::Char(' ') => 32,
+            Key::Char(c) => return Some((0, c as u32)),
+            Key::Ctrl('/') => 31,
+            Key::Ctrl(c @ 'a' ... 'z') => c as u16 - 'a' as u16 + 1,
+            Key::Ctrl(_) => return None,
+            Key::Tab => 9,
+            Key::Enter => 13,
+            Key::Esc => 27,
+            Key::Backspace => 127,
+            Key::F(n @ 1 ... 12) => 65535 - (n as u16 - 1),
+            Key::F(_) => return None,
+            Key::Insert => 65523,
+            Key::Delete => 65522,
+            Key::Home => 65521,
+            Key::End => 65520,
+            Key::PageUp => 65519,
+            Key::PageDown => 65518,
+            Key::Up => 65517,
+            Key::Down => 65516,
+            Key::Left => 65515,
+            Key::Right => 65514
+        };
+        Some((code, 0))
+    }
+}
+
+/// `event` as termbox reported it when asked for raw events: key presses as
+/// Event::KeyEventRaw, if termbox had a code for the key. Other events are left as they are.
+pub fn raw_event(event: Event) -> Event {
+    match event {
+        Event::KeyEvent(Some(key), modifiers) => match key.to_raw() {
+            Some((code, ch)) => Event::KeyEventRaw(raw_modifiers(modifiers), code, ch),
+            None => event
+        },
+        event => event
+    }
+}
+
+fn raw_modifiers(modifiers: Modifiers) -> u8 {
+    if modifiers.contains(MOD_ALT) { RAW_MOD_ALT } else { 0 }
+}
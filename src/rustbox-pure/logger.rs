@@ -0,0 +1,235 @@
+//! A `log` implementation for programs using RustBox.
+//!
+//! While RustBox is running, stderr is hidden behind the UI, so log records written there
+//! would be lost. The logger installed by `init` keeps them in a ring buffer instead, which a
+//! `LogView` can draw, and writes them to stderr once RustBox has exited. Records logged while
+//! RustBox is not running go to stderr directly.
+//!
+//! ```no_run
+//! #[macro_use] extern crate log;
+//! extern crate rustbox;
+//!
+//! use std::default::Default;
+//! use log::LogLevelFilter;
+//! use rustbox::RustBox;
+//! use rustbox::logger::{self, LogView};
+//!
+//! fn main() {
+//!     let log = logger::init(500, LogLevelFilter::Debug).unwrap();
+//!     let rb = RustBox::init(Default::default()).unwrap();
+//!
+//!     info!("started");
+//!
+//!     let (width, height) = (rb.width(), rb.height());
+//!     let view = LogView { level: LogLevelFilter::Info, ..Default::default() };
+//!     view.draw(&rb, &log, 0, height - 5, width, 5);
+//!     rb.present();
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::default::Default;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{self, AtomicUsize};
+
+use log::{self, Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord, SetLoggerError};
+
+use super::{RustBox, Backend, Color, Style, RB_NORMAL, RB_BOLD};
+use super::running::running;
+
+// The buffer of the installed logger, leaked as it lives as long as the process. Zero until
+// the logger is installed.
+static BUFFER: AtomicUsize = atomic::ATOMIC_USIZE_INIT;
+
+/// A log record, as kept in the buffer.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String
+}
+
+struct Ring {
+    entries: VecDeque<Entry>,
+    capacity: usize
+}
+
+/// The records kept while RustBox is running, oldest first.
+#[derive(Clone)]
+pub struct LogBuffer {
+    ring: Arc<Mutex<Ring>>
+}
+
+impl LogBuffer {
+    fn lock(&self) -> MutexGuard<Ring> {
+        match self.ring.lock() {
+            Ok(ring) => ring,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+
+    /// The records at or above `level`, oldest first.
+    pub fn entries(&self, level: LogLevelFilter) -> Vec<Entry> {
+        self.lock().entries.iter().filter(|entry| entry.level <= level).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    fn push(&self, entry: Entry) {
+        let mut ring = self.lock();
+
+        if ring.entries.len() == ring.capacity {
+            ring.entries.pop_front();
+        }
+        ring.entries.push_back(entry);
+    }
+
+    /// Write out all records to stderr and forget them.
+    fn flush(&self) {
+        let entries: Vec<Entry> = self.lock().entries.drain(..).collect();
+
+        let stderr = io::stderr();
+        let mut stderr = stderr.lock();
+        for entry in entries.iter() {
+            let _ = writeln!(stderr, "{}", format_entry(entry));
+        }
+    }
+}
+
+struct Logger {
+    buffer: LogBuffer,
+    level: LogLevelFilter
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = Entry {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: format!("{}", record.args())
+        };
+
+        if running() {
+            self.buffer.push(entry);
+
+            // RustBox may have exited since, and flushed the buffer already.
+            if !running() {
+                self.buffer.flush();
+            }
+        } else {
+            // Anything logged while RustBox was shutting down comes first.
+            self.buffer.flush();
+            let _ = writeln!(io::stderr(), "{}", format_entry(&entry));
+        }
+    }
+}
+
+/// Install the logger, keeping up to `capacity` records at or above `level` while RustBox is
+/// running. Like any logger, it can only be installed once.
+pub fn init(capacity: usize, level: LogLevelFilter) -> Result<LogBuffer, SetLoggerError> {
+    let buffer = LogBuffer {
+        ring: Arc::new(Mutex::new(Ring { entries: VecDeque::with_capacity(capacity), capacity: capacity }))
+    };
+
+    let logger_buffer = buffer.clone();
+    try!(log::set_logger(|max_level| {
+        max_level.set(level);
+        Box::new(Logger { buffer: logger_buffer, level: level })
+    }));
+
+    BUFFER.store(Box::into_raw(Box::new(buffer.clone())) as usize, atomic::Ordering::SeqCst);
+
+    Ok(buffer)
+}
+
+/// Write out the records kept while RustBox was running. Called when RustBox exits.
+pub fn flush() {
+    let buffer = BUFFER.load(atomic::Ordering::SeqCst) as *const LogBuffer;
+
+    if !buffer.is_null() {
+        unsafe { (*buffer).flush(); }
+    }
+}
+
+fn format_entry(entry: &Entry) -> String {
+    format!("{:<5} {}: {}", entry.level, entry.target, entry.message)
+}
+
+/// Draws the most recent records of a LogBuffer into a region of the screen, one per line.
+#[derive(Clone, Copy, Debug)]
+pub struct LogView {
+    /// Only records at or above this level are shown.
+    pub level: LogLevelFilter,
+    /// Whether to show the target (usually the module path) of each record.
+    pub show_target: bool,
+    pub background: Color
+}
+
+impl Default for LogView {
+    fn default() -> Self {
+        LogView {
+            level: LogLevelFilter::Trace,
+            show_target: true,
+            background: Color::Default
+        }
+    }
+}
+
+impl LogView {
+    pub fn draw<B: Backend>(&self, rb: &RustBox<B>, buffer: &LogBuffer,
+                            x: usize, y: usize, width: usize, height: usize) {
+        let entries = buffer.entries(self.level);
+        let shown = if entries.len() > height { &entries[entries.len() - height..] } else { &entries[..] };
+
+        for row in 0..height {
+            for column in 0..width {
+                rb.print_char(x + column, y + row, RB_NORMAL, Color::Default, self.background, ' ');
+            }
+        }
+
+        for (row, entry) in shown.iter().enumerate() {
+            let (color, style) = level_style(entry.level);
+
+            let label = format!("{:<5} ", entry.level);
+            rb.print(x, y + row, style, color, self.background, truncate(&label, width));
+
+            let column = label.len();
+            if column >= width {
+                continue;
+            }
+
+            // Only the first line of a message fits.
+            let message = entry.message.lines().next().unwrap_or("");
+            let text = if self.show_target { format!("{}: {}", entry.target, message) } else { message.to_string() };
+            rb.print(x + column, y + row, RB_NORMAL, Color::Default, self.background, truncate(&text, width - column));
+        }
+    }
+}
+
+fn level_style(level: LogLevel) -> (Color, Style) {
+    match level {
+        LogLevel::Error => (Color::Red, RB_BOLD),
+        LogLevel::Warn => (Color::Yellow, RB_BOLD),
+        LogLevel::Info => (Color::Green, RB_NORMAL),
+        LogLevel::Debug => (Color::Cyan, RB_NORMAL),
+        LogLevel::Trace => (Color::Blue, RB_NORMAL)
+    }
+}
+
+fn truncate(text: &str, width: usize) -> &str {
+    match text.char_indices().nth(width) {
+        Some((index, _)) => &text[..index],
+        None => text
+    }
+}
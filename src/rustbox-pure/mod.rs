@@ -2,22 +2,65 @@ pub mod event;
 pub mod keyboard;
 pub mod cell;
 pub mod style;
+pub mod backend;
+pub mod headless;
+pub mod input;
+#[cfg(feature="log")]
+pub mod logger;
+#[cfg(all(unix, feature="stream"))]
+pub mod stream;
 
 mod running;
+mod sender;
+mod timer;
+mod capture;
+mod child;
+mod panic_hook;
+#[cfg(unix)]
+mod sys;
+#[cfg(any(all(target_os="linux",
+              any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")),
+          target_os="windows"))]
 mod console;
 
-pub use self::event::{Event, Mouse, Key, EventResult};
+#[cfg(target_os="macos")]
+#[path="../rustbox-c/mod.rs"]
+pub mod termbox;
+
+pub use self::event::{Event, Mouse, Key, Signal, EventResult};
+pub use self::event::{Modifiers, MOD_ALT, MOD_CTRL, MOD_SHIFT, MOD_SUPER, MOD_NONE};
 pub use self::cell::{Cell, CellBuffer};
 pub use self::style::{Color, Style, RB_BOLD, RB_UNDERLINE, RB_REVERSE, RB_NORMAL};
+pub use self::backend::{Backend, Size, Location};
+pub use self::headless::Headless;
+pub use self::running::running;
+pub use self::sender::{EventSender, Payload};
+pub use self::timer::TimerId;
+
+#[cfg(any(all(target_os="linux",
+              any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")),
+          target_os="windows"))]
+pub use self::console::Console;
 
-use self::running::running;
-use self::console::{DisplayInfo, Handle, RawEvent, Size, Location};
+/// The backend used by `RustBox::init`.
+#[cfg(any(all(target_os="linux",
+              any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")),
+          target_os="windows"))]
+pub type DefaultBackend = console::Console;
+#[cfg(target_os="macos")]
+pub type DefaultBackend = termbox::Termbox;
 
+use self::capture::{Capture, Captured};
+
+use std::cell::{Ref, RefCell};
 use std::default::Default;
 use std::error::Error;
 use std::{fmt, io, char};
+use std::process::{Command, ExitStatus};
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
 use num::FromPrimitive;
-use libc::c_int;
 use time::Duration;
 
 #[derive(Clone, Copy, Debug)]
@@ -38,16 +81,22 @@ pub enum InputMode {
 #[derive(Debug)]
 pub enum InitError {
     BufferStderrFailed(io::Error),
+    BufferStdoutFailed(io::Error),
     AlreadyOpen,
     UnsupportedTerminal,
-    FailedToOpenTTy,
+    FailedToOpenTTy(PathBuf),
     PipeTrapError,
     Unknown(isize),
 }
 
 impl fmt::Display for InitError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}", self.description())
+        match *self {
+            InitError::FailedToOpenTTy(ref path) => {
+                write!(fmt, "{}: {}", self.description(), path.display())
+            }
+            _ => write!(fmt, "{}", self.description())
+        }
     }
 }
 
@@ -55,9 +104,10 @@ impl Error for InitError {
     fn description(&self) -> &str {
         match *self {
             InitError::BufferStderrFailed(_) => "Could not redirect stderr",
+            InitError::BufferStdoutFailed(_) => "Could not redirect stdout",
             InitError::AlreadyOpen => "RustBox is already open",
             InitError::UnsupportedTerminal => "Unsupported terminal",
-            InitError::FailedToOpenTTy => "Failed to open TTY",
+            InitError::FailedToOpenTTy(_) => "Failed to open TTY",
             InitError::PipeTrapError => "Pipe trap error",
             InitError::Unknown(_) => "Unknown error from Termbox",
         }
@@ -66,6 +116,7 @@ impl Error for InitError {
     fn cause(&self) -> Option<&Error> {
         match *self {
             InitError::BufferStderrFailed(ref e) => Some(e),
+            InitError::BufferStdoutFailed(ref e) => Some(e),
             _ => None
         }
     }
@@ -75,7 +126,8 @@ impl FromPrimitive for InitError {
    fn from_i64(n: i64) -> Option<InitError> {
       match n {
          -1 => Some(InitError::UnsupportedTerminal),
-         -2 => Some(InitError::FailedToOpenTTy),
+         // Termbox always opens the controlling terminal.
+         -2 => Some(InitError::FailedToOpenTTy(PathBuf::from("/dev/tty"))),
          -3 => Some(InitError::PipeTrapError),
          n => Some(InitError::Unknown(n as isize)),
       }
@@ -86,17 +138,136 @@ impl FromPrimitive for InitError {
    }
 }
 
-#[derive(Clone, Copy,Debug)]
+/// The terminal RustBox draws to and reads input from.
+#[derive(Clone, Debug)]
+pub enum Tty {
+    /// The process's stdin and stdout, the default on Windows. Elsewhere this has to be asked
+    /// for, and fails if stdin is not a terminal.
+    Stdio,
+    /// The controlling terminal, opened through `/dev/tty` (the console itself on Windows).
+    /// The default on Linux, as termbox always opened it too.
+    ///
+    /// This leaves the process's stdin and stdout free for data, so a program can read from a
+    /// pipe and write its result to stdout while the UI is shown on the terminal. The terminal
+    /// is restored and closed again when RustBox exits, and buffer_stderr works as usual.
+    Controlling,
+    /// A terminal device opened by path, for instance another pseudo-terminal such as
+    /// `/dev/pts/3`. It is closed again when RustBox exits.
+    ///
+    /// Resizing a terminal other than the controlling one does not signal this process, so
+    /// its size is checked every 100ms instead, here and with Tty::Fds.
+    Path(PathBuf),
+    /// Already open descriptors of a terminal, to read input from and write output to
+    /// respectively. They are left open when RustBox exits.
+    #[cfg(unix)]
+    Fds(RawFd, RawFd),
+}
+
+/// The part of the terminal RustBox draws on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Viewport {
+    /// The whole screen, switching to the alternate screen so that what was on the terminal is
+    /// put back when RustBox exits.
+    Fullscreen,
+    /// A region of the given number of lines, starting at the cursor's line and scrolling the
+    /// terminal up if there is not enough room below it. width() and height() describe the
+    /// region, and what was drawn last stays on the terminal when RustBox exits, with the
+    /// cursor on the line below.
+    Inline(usize),
+}
+
+#[derive(Clone, Debug)]
 pub struct InitOptions {
     /// Use this option to initialize with a specific input mode.
     /// See InputMode enum for details on the variants.
 
     pub input_mode: InputMode,
 
+    /// Use this option to automatically buffer stderr while RustBox is running.  It will be
+    /// written when RustBox exits, and can be read in the meantime with captured_stderr.
+    ///
+    /// Stderr is redirected to an OS pipe, which a background thread drains into memory as
+    /// soon as anything is written to it, so writes never block or fail however much is
+    /// written.
+    ///
     /// NOTE: buffer_stderr remains for API consistency, but is not supported on Windows.
     /// Functionality will eventually converge between Linux/OSX/Windows.
 
     pub buffer_stderr: bool,
+
+    /// Use this option to buffer stdout the same way as buffer_stderr does stderr, for
+    /// instance to keep libraries printing to stdout from drawing over the screen. It can be
+    /// read in the meantime with captured_stdout.
+    ///
+    /// RustBox keeps drawing on the terminal with Tty::Stdio, but this is pointless with
+    /// Tty::Fds if the output descriptor is stdout.
+    ///
+    /// NOTE: buffer_stdout is not supported on Windows.
+
+    pub buffer_stdout: bool,
+
+    /// Use this option to choose the terminal RustBox drives, such as stdin/stdout instead of
+    /// /dev/tty.
+    /// See Tty enum for details on the variants.
+    ///
+    /// NOTE: only Tty::Stdio and Tty::Controlling are supported on Windows and OS X.
+
+    pub tty: Tty,
+
+    /// Use this option to control whether each present is wrapped in a synchronized update
+    /// (DEC private mode 2026), so the terminal shows the frame all at once instead of
+    /// rendering it partway through. `None` asks the terminal whether it supports the mode,
+    /// `Some(..)` forces it on or off without asking.
+    ///
+    /// NOTE: synchronized output is only supported on Linux.
+
+    pub synchronized_output: Option<bool>,
+
+    /// Use this option to draw on only part of the terminal.
+    /// See Viewport enum for details on the variants.
+    ///
+    /// NOTE: Viewport::Inline is not supported on OS X.
+
+    pub viewport: Viewport,
+
+    /// Use this option to install a panic hook while RustBox is running. On a panic, from any
+    /// thread, it gives the terminal back and writes out output buffered by buffer_stderr and
+    /// buffer_stdout before the panic message is printed by the hook that was installed before, so that
+    /// neither ends up lost on the alternate screen. That hook is put back when RustBox exits.
+    ///
+    /// NOTE: the terminal is not given back by the hook on Windows, only when RustBox is dropped.
+
+    pub panic_hook: bool,
+
+    /// Use this option to have SIGINT, SIGTERM, SIGHUP and SIGCONT returned by poll_event and
+    /// peek_event as Event::Signal instead of acting on them as before, so that the program
+    /// can save its state and exit cleanly, giving the terminal back.
+    ///
+    /// NOTE: on Windows console control events are reported instead, and the process is
+    /// ended by the system shortly after the window is closed. signal_events is not
+    /// supported on OS X.
+
+    pub signal_events: bool,
+
+    /// Use this option to enable the kitty keyboard protocol on terminals supporting it. Keys
+    /// are then reported unambiguously, so that for instance Ctrl-i is Key::Ctrl('i') rather
+    /// than Key::Tab and Ctrl-h is not Key::Backspace, and repeats and releases are reported
+    /// as Event::KeyRepeat and Event::KeyRelease. Other terminals ignore it.
+    ///
+    /// NOTE: kitty_keyboard is only supported on Linux, and has no effect on Windows or macOS.
+
+    pub kitty_keyboard: bool,
+
+    /// Use this option to enable bracketed paste on terminals supporting it. Pasted text is
+    /// then returned by poll_event and peek_event as a single Event::Paste, so that newlines
+    /// in it are not taken for Enter. On other terminals pasted text keeps arriving as
+    /// KeyEvents, as if typed. Very long pastes come as several Event::Paste, and a paste the
+    /// terminal has not ended after a second of quiet is taken to have ended.
+    ///
+    /// NOTE: bracketed_paste is only supported on Linux. Elsewhere pasted text arrives as
+    /// KeyEvents.
+
+    pub bracketed_paste: bool,
 }
 
 impl Default for InitOptions {
@@ -104,18 +275,41 @@ impl Default for InitOptions {
         InitOptions {
             input_mode: InputMode::Current,
             buffer_stderr: false,
+            buffer_stdout: false,
+            tty: if cfg!(target_os="linux") { Tty::Controlling } else { Tty::Stdio },
+            synchronized_output: None,
+            viewport: Viewport::Fullscreen,
+            panic_hook: false,
+            signal_events: false,
+            kitty_keyboard: false,
+            bracketed_paste: false,
         }
     }
 }
 
 #[allow(missing_copy_implementations)]
-pub struct RustBox {
-    handle: Handle,
-    cell_buffer: CellBuffer,
-    default_attr: u16,
+pub struct RustBox<B: Backend = DefaultBackend> {
+    // Drawing takes &self, as it does with termbox, so what it changes is kept in a RefCell.
+    state: RefCell<State<B>>,
+    sender: EventSender,
+    stdout: Option<Captured>,
+    stderr: Option<Captured>,
+    _captures: Vec<Capture>,
+    _panic_hook: Option<panic_hook::PanicGuard>,
     /* Note that running *MUST* be the last field in the destructor, since destructors run in
     top-down order. Otherwise it will not properly protect the above fields. */
-    _running: running::RunningGuard
+    _running: Option<running::RunningGuard>
+}
+
+/// The backend and buffers of a RustBox, borrowed by each of its methods in turn.
+struct State<B: Backend> {
+    backend: B,
+    cell_buffer: CellBuffer,
+    // What the last present left on the display, or None if it has to be redrawn in full.
+    front_buffer: Option<CellBuffer>,
+    clear_cell: Cell,
+    cursor: Option<Location>,
+    timers: timer::Timers
 }
 
 impl RustBox {
@@ -134,36 +328,71 @@ impl RustBox {
     /// use std::default::Default;
     /// let rb = RustBox::init(InitOptions {
     ///     input_mode: rustbox::InputMode::Esc,
-    ///    buffer_stderr: false
+    ///     ..Default::default()
     /// });
     /// ```
     ///
     /// Again, buffer_stderr is unimplemented on windows.
 
     pub fn init(opts: InitOptions) -> Result<RustBox, InitError> {
-        let running = match running::run() {
-            Some(r) => r,
-            None => return Err(InitError::AlreadyOpen),
+        RustBox::with_backend(DefaultBackend::new(), opts)
+    }
+}
+
+impl<B: Backend> RustBox<B> {
+    /// Initialize Rustbox on a backend other than the platform's default one.
+    pub fn with_backend(mut backend: B, opts: InitOptions) -> Result<RustBox<B>, InitError> {
+        let running = if backend.is_terminal() {
+            match running::run() {
+                Some(r) => Some(r),
+                None => return Err(InitError::AlreadyOpen),
+            }
+        } else {
+            None
         };
 
-        let handle = console::begin_display();
+        try!(backend.begin_display(&opts));
 
-        // For now enable mouse input, ctrl-c by default
-        console::set_mode(handle, true, true);
+        // Captured only now, so that the backend has got hold of the real stdout already.
+        let captures = match capture_output(&opts) {
+            Ok(captures) => captures,
+            Err(e) => {
+                backend.finish_display();
+                return Err(e);
+            }
+        };
+        let (stdout, stderr) = captured(&captures);
+
+        // With the hook installed, captured output is written out by it, and only after the
+        // terminal has been given back.
+        let (captures, panic_guard) = if opts.panic_hook {
+            (Vec::new(), Some(panic_hook::install(backend.panic_restorer(), captures)))
+        } else {
+            (captures, None)
+        };
 
-        // Create resize event hook
-        // ...
+        let Size {width: width, height: height} = backend.size();
 
-        let Size {width: width, height: height} = console::visible_size(handle);
+        let clear_cell = Cell { ch: ' ', fg: Color::Default, bg: Color::Black, sty: style::RB_NORMAL };
+        let cell_buffer = CellBuffer::new(width, height, clear_cell);
 
-        let default_attr = console::translate_attr(Color::Default, Color::Black, style::RB_NORMAL);
-        let cell_buffer = CellBuffer::new(width, height, b' ', default_attr);
+        let sender = EventSender::new(backend.waker());
 
         // Create the RustBox.
-        let mut rb = RustBox {
-            handle: handle,
-            cell_buffer: cell_buffer,
-            default_attr: default_attr,
+        let rb = RustBox {
+            state: RefCell::new(State {
+                backend: backend,
+                cell_buffer: cell_buffer,
+                front_buffer: None,
+                clear_cell: clear_cell,
+                cursor: None,
+                timers: timer::Timers::new()
+            }),
+            sender: sender,
+            stdout: stdout,
+            stderr: stderr,
+            _captures: captures,
+            _panic_hook: panic_guard,
             _running: running
         };
 
@@ -177,131 +406,630 @@ impl RustBox {
 
     pub fn shutdown(self) {}
 
+    /// The backend, borrowed until the returned guard is dropped. Drawing or reading events
+    /// while it is borrowed panics.
+    pub fn backend(&self) -> Ref<B> {
+        Ref::map(self.state.borrow(), |state| &state.backend)
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.state.get_mut().backend
+    }
+
     pub fn width(&self) -> usize {
-        self.cell_buffer.width
+        self.state.borrow().cell_buffer.width
     }
 
     pub fn height(&self) -> usize {
-        self.cell_buffer.height
+        self.state.borrow().cell_buffer.height
     }
 
-    pub fn clear(&mut self) {
-        let Size {width: width, height: height} = console::visible_size(self.handle);
+    pub fn clear(&self) {
+        self.state.borrow_mut().clear();
+    }
 
-        // Resize backbuffer if its size doesn't match the visible size.
-        if width != self.cell_buffer.width || height != self.cell_buffer.height {
-            self.cell_buffer.resize_blindly(width, height);
+    pub fn set_clear_attributes(&self, foreground: Color, background: Color, style: Style) {
+        self.state.borrow_mut().clear_cell = Cell { ch: ' ', fg: foreground, bg: background, sty: style };
+    }
+
+    pub fn present(&self) {
+        self.state.borrow_mut().present();
+    }
+
+    /// Redraw the whole screen on the next present, rather than only what changed. Use this
+    /// when something other than RustBox may have drawn on the terminal.
+    pub fn force_redraw(&self) {
+        self.state.borrow_mut().front_buffer = None;
+    }
+
+    /// Print lines above an inline viewport, where they stay on the terminal and scroll up
+    /// into its scrollback like normal output, while the viewport stays below them. Lines
+    /// longer than the terminal is wide are cut off. The viewport is presented again right
+    /// away, so draw what it should show before calling this.
+    ///
+    /// Only has an effect with Viewport::Inline.
+    pub fn insert_before(&self, lines: &[Vec<Cell>]) {
+        let mut state = self.state.borrow_mut();
+
+        state.backend.insert_before(lines);
+        state.front_buffer = None;
+        state.present();
+    }
+
+    pub fn set_cursor(&self, x: isize, y: isize) {
+        self.state.borrow_mut().set_cursor(x, y);
+    }
+
+    pub fn change_cell(&self, x: usize, y: usize, ch: u32, fg: Color, bg: Color, sty: Style) {
+        self.state.borrow_mut().change_cell(x, y, ch, fg, bg, sty);
+    }
+
+    pub fn put_cell(&self, x: usize, y: usize, cell: Cell)
+    {
+        self.change_cell(x, y, cell.ch as u32, cell.fg, cell.bg, cell.sty);
+    }
+
+    pub fn print(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        let mut state = self.state.borrow_mut();
+
+        for (i, ch) in s.chars().enumerate() {
+            state.change_cell(x + i, y, ch as u32, fg, bg, sty);
+        }
+    }
+
+    pub fn print_char(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
+        self.change_cell(x, y, ch as u32, fg, bg, sty);
+    }
+
+    pub fn poll_event(&self, raw: bool) -> EventResult {
+        /* Don't like the way this is implemented. I think Event::NoEvent is un-rustic,
+        should be indicated by None instead. */
+
+        self.read_event(None, raw)
+    }
+
+    pub fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
+        self.read_event(Some(timeout), raw)
+    }
+
+    /// A handle other threads can use to send events to this RustBox, or wake it up while it
+    /// waits for input.
+    ///
+    /// ```no_run
+    /// use std::thread;
+    /// use rustbox::{RustBox, Event};
+    /// use std::default::Default;
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    ///
+    /// struct Downloaded(String);
+    ///
+    /// let sender = rb.event_sender();
+    /// thread::spawn(move || {
+    ///     // ... download something ...
+    ///     sender.send(Downloaded("notes.txt".to_string()));
+    /// });
+    ///
+    /// if let Ok(Event::User(payload)) = rb.poll_event(false) {
+    ///     if let Some(&Downloaded(ref name)) = payload.downcast_ref::<Downloaded>() {
+    ///         // ... show it ...
+    ///     }
+    /// }
+    /// ```
+    pub fn event_sender(&self) -> EventSender {
+        self.sender.clone()
+    }
+
+    /// Have poll_event and peek_event also wait for `fd`, such as a socket or pipe, and return
+    /// `Event::Fd(token)` when it becomes readable. It is reported for as long as it stays
+    /// readable, so read from it before polling again. Registering it again replaces its
+    /// token.
+    ///
+    /// NOTE: register_fd is only supported on Linux.
+    #[cfg(unix)]
+    pub fn register_fd(&self, fd: RawFd, token: usize) -> io::Result<()> {
+        if self.state.borrow_mut().backend.watch_fd(fd, token) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::Other, "backend cannot wait for other descriptors"))
         }
+    }
+
+    /// Stop waiting for a descriptor registered with register_fd.
+    #[cfg(unix)]
+    pub fn deregister_fd(&self, fd: RawFd) {
+        self.state.borrow_mut().backend.unwatch_fd(fd);
+    }
+
+    /// Have poll_event and peek_event return `Event::Timer(id)` once `delay` has passed.
+    pub fn start_timer(&self, delay: Duration) -> TimerId {
+        self.state.borrow_mut().timers.start(delay, None)
+    }
 
-        let char_slice = self.cell_buffer.char_buffer.as_mut_slice();
-        let attr_slice = self.cell_buffer.attr_buffer.as_mut_slice();
+    /// Have poll_event and peek_event return `Event::Timer(id)` every `interval`, until the
+    /// timer is cancelled. Expiries are kept to a fixed schedule however long handling each
+    /// one takes, and ones missed entirely are skipped. Intervals shorter than a millisecond
+    /// are taken as one.
+    ///
+    /// ```no_run
+    /// extern crate time;
+    /// extern crate rustbox;
+    /// use rustbox::{RustBox, Event};
+    /// use std::default::Default;
+    /// use time::Duration;
+    ///
+    /// # fn main() {
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    ///
+    /// let frame = rb.start_repeating_timer(Duration::milliseconds(30));
+    /// loop {
+    ///     match rb.poll_event(false) {
+    ///         Ok(Event::Timer(id)) if id == frame => rb.present(),
+    ///         _ => { }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn start_repeating_timer(&self, interval: Duration) -> TimerId {
+        self.state.borrow_mut().timers.start(interval, Some(interval))
+    }
+
+    /// Stop a timer, so that it is not returned anymore.
+    pub fn cancel_timer(&self, id: TimerId) {
+        self.state.borrow_mut().timers.cancel(id);
+    }
+
+    /// Time left until the next timer expires, or None without timers. Programs driving
+    /// RustBox from an event loop of their own should wait no longer than this before calling
+    /// read_available_events.
+    pub fn next_timer(&self) -> Option<Duration> {
+        self.state.borrow().timers.remaining()
+    }
+
+    /// Every event that can be read without waiting, for programs driving RustBox from an
+    /// event loop of their own instead of poll_event. Wait for its descriptor (see AsRawFd)
+    /// to become readable, then call this.
+    ///
+    /// Errors are returned in place, among the events read before and after them.
+    pub fn read_available_events(&self, raw: bool) -> Vec<EventResult> {
+        let mut events = Vec::new();
+
+        loop {
+            let event = self.read_event(Some(Duration::zero()), raw);
+
+            match event {
+                Ok(Event::NoEvent) => break,
+                // A registered descriptor is reported until it is read from.
+                Ok(Event::Fd(token)) => {
+                    if events.iter().any(|e| match *e { Ok(Event::Fd(t)) => t == token, _ => false }) {
+                        break;
+                    }
+                }
+                _ => {}
+            }
 
-        for i in 0..char_slice.len() {
-            char_slice[i] = b' ';
-            attr_slice[i] = self.default_attr;
+            events.push(event);
         }
+
+        events
+    }
+
+    /// The events poll_event would return, as a stream driven by the reactor of `handle`. See
+    /// the `stream` module.
+    #[cfg(all(unix, feature="stream"))]
+    pub fn events(&self, raw: bool, handle: &::tokio_core::reactor::Handle) -> io::Result<stream::Events<B>>
+        where B: AsRawFd
+    {
+        stream::Events::new(self, raw, handle)
+    }
+
+    /// Run another program on the terminal, such as an editor or pager, and wait for it to
+    /// finish. The terminal is given back for as long as it runs, and taken over again and
+    /// redrawn with the last frame afterwards. Like system(3), SIGINT and SIGQUIT only reach
+    /// the program while it runs, so Ctrl-c in it does not end this one as well. It writes to
+    /// the real stdout and stderr even if they are being buffered.
+    ///
+    /// ```no_run
+    /// use std::process::Command;
+    /// use rustbox::RustBox;
+    /// use std::default::Default;
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    /// rb.run_external(Command::new("vi").arg("notes.txt")).unwrap();
+    /// ```
+    pub fn run_external(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        let mut state = self.state.borrow_mut();
+
+        state.backend.suspend();
+        let status = child::run(command, &self._captures);
+        state.backend.resume();
+
+        state.front_buffer = None;
+        state.present();
+
+        status
+    }
+
+    /// Everything written to stdout since RustBox started, if buffer_stdout is set.
+    pub fn captured_stdout(&self) -> Option<String> {
+        self.stdout.as_ref().map(|captured| captured.text())
     }
 
-    pub fn set_clear_attributes(&mut self, foreground: Color, background: Color, style: Style) {
-        self.default_attr = console::translate_attr(foreground, background, style);
+    /// Everything written to stderr since RustBox started, if buffer_stderr is set. Useful to
+    /// show diagnostics in the UI while it runs.
+    pub fn captured_stderr(&self) -> Option<String> {
+        self.stderr.as_ref().map(|captured| captured.text())
     }
 
-    pub fn present(&mut self) {
-        let Size {width: width, height: height} = console::visible_size(self.handle);
+    fn read_event(&self, timeout: Option<Duration>, raw: bool) -> EventResult {
+        self.state.borrow_mut().read_event(&self.sender, timeout, raw)
+    }
+
+    pub fn set_input_mode(&self, mode: InputMode) {
+        self.state.borrow_mut().backend.set_input_mode(mode);
+    }
+}
+
+impl<B: Backend> State<B> {
+    fn clear(&mut self) {
+        let Size {width: width, height: height} = self.backend.size();
 
         // Resize backbuffer if its size doesn't match the visible size.
         if width != self.cell_buffer.width || height != self.cell_buffer.height {
-            self.cell_buffer.resize(width, height, b' ', self.default_attr);
+            self.cell_buffer.resize_blindly(width, height);
+        }
+
+        for cell in self.cell_buffer.cells.iter_mut() {
+            *cell = self.clear_cell;
         }
+    }
+
+    fn present(&mut self) {
+        let Size {width: width, height: height} = self.backend.size();
+
+        // Resize backbuffer if its size doesn't match the visible size.
+        if width != self.cell_buffer.width || height != self.cell_buffer.height {
+            self.cell_buffer.resize(width, height, self.clear_cell);
+        }
+
+        let front = match self.front_buffer.take() {
+            Some(front) => if front.width == width && front.height == height { Some(front) } else { None },
+            None => None
+        };
 
-        let char_slice = self.cell_buffer.char_buffer.as_slice();
-        let attr_slice = self.cell_buffer.attr_buffer.as_slice();
+        match front {
+            Some(ref front) => {
+                // Only send the runs of cells that changed since the last present.
+                for line in 0..height {
+                    let back_line = self.cell_buffer.line(line);
+                    for (start, end) in changed_runs(front.line(line), back_line) {
+                        let origin = Location {x: start, y: line};
+                        self.backend.write_cells(&back_line[start..end], origin);
+                    }
+                }
+            }
+            None => {
+                self.backend.reset();
 
-        // Copy line-by-line, since buffer width is not equal to visible width.
-        for line in 0..height {
-            let index = line * width;
+                // Copy line-by-line, since buffer width is not equal to visible width.
+                for line in 0..height {
+                    let origin = Location {x: 0, y: line};
+                    self.backend.write_cells(self.cell_buffer.line(line), origin);
+                }
+            }
+        }
 
-            let char_subslice = &char_slice[index..(index + width)];
-            let attr_subslice = &attr_slice[index..(index + width)];
+        let mut front = front.unwrap_or_else(|| self.cell_buffer.clone());
+        front.clone_from(&self.cell_buffer);
+        self.front_buffer = Some(front);
 
-            let origin = Location {x: 0, y: line};
-            console::write_characters(self.handle, char_subslice, origin);
-            console::write_attributes(self.handle, attr_subslice, origin);
+        // Writing moves the cursor on terminals, put it back where it was asked to be.
+        if self.cursor.is_some() {
+            self.backend.set_cursor(self.cursor);
         }
+
+        self.backend.flush();
     }
 
-    pub fn set_cursor(&self, x: isize, y: isize) {
+    fn set_cursor(&mut self, x: isize, y: isize) {
         if x == -1 && y == -1 {
-            console::set_cursor_visible(self.handle, false);
+            self.cursor = None;
+            self.backend.set_cursor(None);
         }
         else {
             let location = Location {x: x as usize, y: y as usize};
-            let size = console::visible_size(self.handle);
+            let size = self.backend.size();
 
             if location.x < size.width && location.y < size.height {
-                console::set_cursor_location(self.handle, location);
-                console::set_cursor_visible(self.handle, true);
+                self.cursor = Some(location);
+                self.backend.set_cursor(Some(location));
             }
         }
     }
 
-    pub fn change_cell(&mut self, x: usize, y: usize, ch: u32, fg: Color, bg: Color, sty: Style) {
+    fn change_cell(&mut self, x: usize, y: usize, ch: u32, fg: Color, bg: Color, sty: Style) {
         let width = self.cell_buffer.width;
         let height = self.cell_buffer.height;
 
         if x < width && y < height {
-            let attr = console::translate_attr(fg, bg, sty);
-
-            let char_slice = self.cell_buffer.char_buffer.as_mut_slice();
-            let attr_slice = self.cell_buffer.attr_buffer.as_mut_slice();
-
             let index = (y * width) + x;
-            char_slice[index] = ch as u8;
-            attr_slice[index] = attr;
+            self.cell_buffer.cells[index] = Cell {
+                ch: char::from_u32(ch).unwrap_or(' '),
+                fg: fg,
+                bg: bg,
+                sty: sty
+            };
         }
     }
 
-    pub fn put_cell(&mut self, x: usize, y: usize, cell: Cell)
-    {
-        self.change_cell(x, y, cell.ch as u32, cell.fg, cell.bg, cell.sty);
-    }
+    fn read_event(&mut self, sender: &EventSender, timeout: Option<Duration>, raw: bool) -> EventResult {
+        if let Some(payload) = sender.take() {
+            return Ok(Event::User(payload));
+        }
+        if let Some(id) = self.timers.expire() {
+            return Ok(Event::Timer(id));
+        }
 
-    pub fn print(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        let event = self.backend.read_input(self.timers.clamp(timeout), raw);
 
-    }
+        match event {
+            // Woken up by the sender, sent something while timing out, or stopped waiting for a
+            // timer.
+            Ok(Event::NoEvent) => {
+                if let Some(payload) = sender.take() {
+                    return Ok(Event::User(payload));
+                }
+                if let Some(id) = self.timers.expire() {
+                    return Ok(Event::Timer(id));
+                }
+            }
+            // Have the buffer match the new size by the time the caller draws for it. What is on
+            // the terminal is no longer known, so the next frame is drawn in full.
+            Ok(Event::ResizeEvent(width, height)) => {
+                self.cell_buffer.resize(width as usize, height as usize, self.clear_cell);
+                self.front_buffer = None;
+            }
+            // Whatever was on the terminal while stopped is still there, paint over it.
+            Ok(Event::ResumeEvent) | Ok(Event::Signal(Signal::Continue)) => {
+                self.front_buffer = None;
+                self.present();
+            }
+            _ => {}
+        }
 
-    pub fn print_char(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
+        event
+    }
+}
 
+/// A descriptor that becomes readable when poll_event would have something to return, for
+/// waiting on it in an event loop of the program's own and then calling
+/// read_available_events. It covers the terminal input as well as resizes, signals, wakeups
+/// and descriptors registered with register_fd.
+///
+/// NOTE: only backends with such a descriptor, such as the Linux console, implement AsRawFd.
+/// Termbox, used on OS X, and Headless do not.
+#[cfg(unix)]
+impl<B: Backend + AsRawFd> AsRawFd for RustBox<B> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.state.borrow().backend.as_raw_fd()
     }
+}
 
-    pub fn poll_event(&self, raw: bool) -> EventResult {
-        /* Don't like the way this is implemented. I think Event::NoEvent is un-rustic,
-        should be indicated by None instead.
+/// Unchanged cells between two changed ones are rewritten rather than skipped when there are
+/// no more than this many, since moving the cursor costs about as much as writing them.
+const RUN_GAP: usize = 4;
 
-        Also there is currently no error-handling in wincon.rs, so Err result is not used. */
+/// Ranges of cells that differ between two lines of the same width, as (start, end) pairs.
+fn changed_runs(front: &[Cell], back: &[Cell]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
 
-        let raw_event = console::read_input(self.handle);
+    for (x, (old, new)) in front.iter().zip(back.iter()).enumerate() {
+        if old == new {
+            continue;
+        }
 
-        if let Some(event) = console::translate_event(raw_event) { Ok(event) }
-        else { Ok(Event::NoEvent)  }
-    }
+        let extends_last = match runs.last() {
+            Some(&(_, end)) => x - end <= RUN_GAP,
+            None => false
+        };
 
-    pub fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
-        Ok(Event::NoEvent)
+        if extends_last {
+            let last = runs.len() - 1;
+            runs[last].1 = x + 1;
+        } else {
+            runs.push((x, x + 1));
+        }
     }
 
-    pub fn set_input_mode(&self, mode: InputMode) {
+    runs
+}
+
+/// Start capturing stdout and stderr as asked for, in that order.
+#[cfg(unix)]
+fn capture_output(opts: &InitOptions) -> Result<Vec<Capture>, InitError> {
+    let mut captures = Vec::new();
 
+    if opts.buffer_stdout {
+        captures.push(try!(Capture::stdout().map_err(|e| InitError::BufferStdoutFailed(e))));
     }
+    if opts.buffer_stderr {
+        captures.push(try!(Capture::stderr().map_err(|e| InitError::BufferStderrFailed(e))));
+    }
+
+    Ok(captures)
+}
+
+#[cfg(target_os="windows")]
+fn capture_output(_: &InitOptions) -> Result<Vec<Capture>, InitError> {
+    Ok(Vec::new())
+}
+
+/// What is captured of stdout and stderr respectively.
+#[cfg(unix)]
+fn captured(captures: &[Capture]) -> (Option<Captured>, Option<Captured>) {
+    let find = |fd| captures.iter().find(|capture| capture.fd() == fd).map(|capture| capture.captured());
+    (find(1), find(2))
 }
 
-impl Drop for RustBox {
+#[cfg(target_os="windows")]
+fn captured(_: &[Capture]) -> (Option<Captured>, Option<Captured>) {
+    (None, None)
+}
+
+impl<B: Backend> Drop for RustBox<B> {
     fn drop(&mut self) {
         /* Since only one instance of the RustBox is ever accessible, we should not
         need to do this atomically.
         NOTE: we should definitely have RUSTBOX_RUNNING = true here.*/
 
-        /* See sibling comment for console::startDisplay(). Will receive inst of DisplayInfo */
-        console::finish_display(self.handle);
+        self.state.get_mut().backend.finish_display();
+
+        // Put back stdout and stderr, and stop running, so that records logged from now on go
+        // to stderr. Those logged until then are written out after the output captured.
+        self._captures.clear();
+        self._panic_hook = None;
+        self._running = None;
+        flush_log();
+    }
+}
+
+#[cfg(feature="log")]
+fn flush_log() {
+    logger::flush();
+}
+
+#[cfg(not(feature="log"))]
+fn flush_log() {}
+
+#[cfg(test)]
+mod tests {
+    use std::default::Default;
+    use time::Duration;
+
+    use super::{changed_runs, RustBox, InitError, InitOptions, RUN_GAP};
+    use super::backend::{Backend, Size, Location};
+    use super::cell::Cell;
+    use super::event::{Event, EventResult};
+    use super::headless::Headless;
+    use super::style::{Color, RB_NORMAL};
+
+    /// Headless, keeping track of what present() writes.
+    struct Recording {
+        headless: Headless,
+        writes: Vec<(Location, usize)>,
+        resets: usize
+    }
+
+    impl Backend for Recording {
+        fn begin_display(&mut self, opts: &InitOptions) -> Result<(), InitError> {
+            self.headless.begin_display(opts)
+        }
+
+        fn finish_display(&mut self) {
+            self.headless.finish_display();
+        }
+
+        fn size(&self) -> Size {
+            self.headless.size()
+        }
+
+        fn write_cells(&mut self, cells: &[Cell], origin: Location) {
+            self.writes.push((origin, cells.len()));
+            self.headless.write_cells(cells, origin);
+        }
+
+        fn set_cursor(&mut self, location: Option<Location>) {
+            self.headless.set_cursor(location);
+        }
+
+        fn reset(&mut self) {
+            self.resets += 1;
+        }
+
+        fn read_input(&mut self, timeout: Option<Duration>, raw: bool) -> EventResult {
+            self.headless.read_input(timeout, raw)
+        }
+    }
+
+    fn recording(width: usize, height: usize, events: Vec<Event>) -> RustBox<Recording> {
+        let mut headless = Headless::new(width, height);
+        for event in events {
+            headless.push_event(event);
+        }
+
+        let backend = Recording { headless: headless, writes: Vec::new(), resets: 0 };
+        RustBox::with_backend(backend, Default::default()).unwrap()
+    }
+
+    fn line(text: &str) -> Vec<Cell> {
+        text.chars()
+            .map(|ch| Cell { ch: ch, fg: Color::Default, bg: Color::Black, sty: RB_NORMAL })
+            .collect()
+    }
+
+    fn full_writes(width: usize, height: usize) -> Vec<(Location, usize)> {
+        (0..height).map(|y| (Location {x: 0, y: y}, width)).collect()
+    }
+
+    #[test]
+    fn unchanged_line() {
+        assert_eq!(changed_runs(&line("abcdef"), &line("abcdef")), vec![]);
+    }
+
+    #[test]
+    fn runs_merge_across_short_gaps() {
+        // The first gap is RUN_GAP cells wide, the second one wider.
+        let gap: String = (0..RUN_GAP).map(|_| '.').collect();
+        let front = line(&format!(".{}.{}..", gap, gap));
+        let back = line(&format!("x{}x{}.x", gap, gap));
+
+        assert_eq!(changed_runs(&front, &back),
+                   vec![(0, RUN_GAP + 2), (2 * RUN_GAP + 3, 2 * RUN_GAP + 4)]);
+    }
+
+    #[test]
+    fn present_writes_what_changed() {
+        let rb = recording(10, 3, vec![]);
+        rb.present();
+        assert_eq!(rb.backend().writes, full_writes(10, 3));
+        assert_eq!(rb.backend().resets, 1);
+
+        rb.state.borrow_mut().backend.writes.clear();
+        rb.print(2, 1, RB_NORMAL, Color::White, Color::Black, "ab");
+        rb.present();
+
+        assert_eq!(rb.backend().writes, vec![(Location {x: 2, y: 1}, 2)]);
+        assert_eq!(rb.backend().resets, 1);
+    }
+
+    #[test]
+    fn resize_redraws_everything() {
+        let rb = recording(10, 3, vec![Event::ResizeEvent(6, 4)]);
+        rb.present();
+
+        match rb.poll_event(false) {
+            Ok(Event::ResizeEvent(6, 4)) => {}
+            _ => panic!("expected the resize")
+        }
+
+        rb.state.borrow_mut().backend.writes.clear();
+        rb.present();
+
+        assert_eq!(rb.backend().writes, full_writes(6, 4));
+        assert_eq!(rb.backend().resets, 2);
+    }
+
+    #[test]
+    fn force_redraw() {
+        let rb = recording(10, 3, vec![]);
+        rb.present();
+        rb.state.borrow_mut().backend.writes.clear();
+
+        rb.present();
+        assert_eq!(rb.backend().writes, vec![]);
+
+        rb.force_redraw();
+        rb.present();
+
+        assert_eq!(rb.backend().writes, full_writes(10, 3));
+        assert_eq!(rb.backend().resets, 2);
     }
 }
@@ -0,0 +1,74 @@
+use std::panic::{self, PanicInfo};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::capture::Capture;
+
+/// Gives the terminal back from any thread, see `Backend::panic_restorer`.
+pub type Restorer = Box<Fn() + Send + Sync>;
+
+type Hook = Box<Fn(&PanicInfo) + Send + Sync>;
+
+struct State {
+    restore: Option<Restorer>,
+    captures: Vec<Capture>,
+    previous: Option<Hook>
+}
+
+/// Keeps a panic hook installed that gives the terminal back and writes out captured output
+/// before the panic message is printed. The hook that was installed before is put back when
+/// this is dropped.
+pub struct PanicGuard {
+    state: Arc<Mutex<State>>
+}
+
+pub fn install(restore: Option<Restorer>, captures: Vec<Capture>) -> PanicGuard {
+    let state = Arc::new(Mutex::new(State {
+        restore: restore,
+        captures: captures,
+        previous: Some(panic::take_hook())
+    }));
+
+    let hook_state = state.clone();
+    panic::set_hook(Box::new(move |info| {
+        // A panic while holding the lock elsewhere must not keep the message from printing.
+        let mut state = match hook_state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        if let Some(restore) = state.restore.take() {
+            restore();
+        }
+
+        // Dropping a capture writes out what it captured.
+        state.captures.clear();
+
+        if let Some(ref previous) = state.previous {
+            previous(info);
+        }
+    }));
+
+    PanicGuard { state: state }
+}
+
+impl Drop for PanicGuard {
+    fn drop(&mut self) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        state.restore = None;
+        state.captures.clear();
+
+        // The hook cannot be changed while panicking. It is harmless to leave it in place then,
+        // as all it does now is call the previous one.
+        if !thread::panicking() {
+            let _ = panic::take_hook();
+            if let Some(previous) = state.previous.take() {
+                panic::set_hook(previous);
+            }
+        }
+    }
+}
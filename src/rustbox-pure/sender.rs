@@ -0,0 +1,75 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Hands events to a RustBox from other threads, for instance to have the UI redrawn when
+/// work done in the background has finished. Get one with `RustBox::event_sender` and clone
+/// it for as many threads as needed.
+///
+/// Payloads sent are returned by `poll_event` and `peek_event` as `Event::User`, in the
+/// order they were sent, waking them up if they are waiting for input. Sending to a RustBox
+/// that has been dropped does nothing.
+#[derive(Clone)]
+pub struct EventSender {
+    queue: Arc<Mutex<VecDeque<Payload>>>,
+    wake: Arc<Box<Fn() + Send + Sync>>
+}
+
+/// A value sent with an EventSender, as returned in `Event::User`.
+#[derive(Clone)]
+pub struct Payload(Arc<Any + Send + Sync>);
+
+impl Payload {
+    /// Whether the value sent is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.downcast_ref::<T>().is_some()
+    }
+
+    /// The value sent, if it is a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        let value: &Any = &*self.0;
+        value.downcast_ref::<T>()
+    }
+}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Payload")
+    }
+}
+
+impl EventSender {
+    pub fn new(wake: Option<Box<Fn() + Send + Sync>>) -> EventSender {
+        EventSender {
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            // Backends without a waker never wait for long.
+            wake: Arc::new(wake.unwrap_or_else(|| Box::new(|| {})))
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<VecDeque<Payload>> {
+        match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+
+    /// Queue `payload` to be returned as `Event::User`. Get it back with
+    /// `Payload::downcast_ref`.
+    pub fn send<T: Any + Send + Sync>(&self, payload: T) {
+        self.lock().push_back(Payload(Arc::new(payload)));
+        self.wake();
+    }
+
+    /// Make a `poll_event` or `peek_event` that is waiting for input return `Event::NoEvent`
+    /// now. If none is waiting, the next one returns `Event::NoEvent` straight away.
+    pub fn wake(&self) {
+        (self.wake)();
+    }
+
+    /// The next payload sent, if any.
+    pub fn take(&self) -> Option<Payload> {
+        self.lock().pop_front()
+    }
+}
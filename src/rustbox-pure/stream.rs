@@ -0,0 +1,128 @@
+//! Events as a `futures` stream, for programs that are asynchronous already.
+//!
+//! The stream is driven by a tokio-core reactor: the descriptor of the RustBox (see its
+//! `AsRawFd`) is registered with it, along with a timeout for the next timer, and the task is
+//! notified when either is due. No thread waits for input, and since the stream only borrows
+//! the RustBox, the program can keep drawing while it reads from it.
+//!
+//! ```no_run
+//! extern crate futures;
+//! extern crate rustbox;
+//! extern crate tokio_core;
+//!
+//! use std::default::Default;
+//! use futures::Stream;
+//! use rustbox::{RustBox, Event, Key};
+//! use tokio_core::reactor::Core;
+//!
+//! fn main() {
+//!     let mut core = Core::new().unwrap();
+//!     let rb = RustBox::init(Default::default()).unwrap();
+//!
+//!     let quit = rb.events(false, &core.handle()).unwrap()
+//!         .take_while(|event| Ok(match *event {
+//!             Event::KeyEvent(Some(Key::Char('q')), _) => false,
+//!             _ => true
+//!         }))
+//!         .for_each(|_| {
+//!             rb.present();
+//!             Ok(())
+//!         });
+//!
+//!     core.run(quit).unwrap();
+//! }
+//! ```
+
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration as StdDuration;
+
+use futures::{Async, Future, Poll, Stream};
+use mio::{self, Evented, PollOpt, Ready, Token};
+use mio::unix::EventedFd;
+use tokio_core::reactor::{Handle, PollEvented, Timeout};
+use time::Duration;
+
+use super::{RustBox, Backend, Event};
+use super::event::EventError;
+
+/// Stream of the events `poll_event` would return, see `RustBox::events`.
+pub struct Events<'a, B: Backend + AsRawFd + 'a> {
+    rb: &'a RustBox<B>,
+    raw: bool,
+    input: PollEvented<Descriptor>,
+    // Expires along with the next timer. Replaced rather than added to, so that only one
+    // wake-up is pending at a time.
+    timeout: Option<Timeout>,
+    handle: Handle
+}
+
+/// A descriptor owned by the backend, registered with the reactor as it is.
+struct Descriptor(RawFd);
+
+impl Evented for Descriptor {
+    fn register(&self, poll: &mio::Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &mio::Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        EventedFd(&self.0).deregister(poll)
+    }
+}
+
+impl<'a, B: Backend + AsRawFd> Events<'a, B> {
+    pub fn new(rb: &'a RustBox<B>, raw: bool, handle: &Handle) -> io::Result<Events<'a, B>> {
+        let input = try!(PollEvented::new(Descriptor(rb.as_raw_fd()), handle));
+        Ok(Events { rb: rb, raw: raw, input: input, timeout: None, handle: handle.clone() })
+    }
+
+    /// Have the current task notified when the next timer expires. Returns whether it has
+    /// expired already.
+    fn wait_for_timer(&mut self) -> bool {
+        let remaining = self.rb.state.borrow().timers.clamp(None);
+
+        self.timeout = match remaining {
+            Some(remaining) => {
+                let remaining = remaining.to_std().unwrap_or(StdDuration::new(0, 0));
+                Timeout::new(remaining, &self.handle).ok()
+            }
+            None => None
+        };
+
+        match self.timeout {
+            Some(ref mut timeout) => match timeout.poll() {
+                Ok(Async::Ready(())) => true,
+                _ => false
+            },
+            None => false
+        }
+    }
+}
+
+impl<'a, B: Backend + AsRawFd> Stream for Events<'a, B> {
+    type Item = Event;
+    type Error = EventError;
+
+    fn poll(&mut self) -> Poll<Option<Event>, EventError> {
+        loop {
+            match try!(self.rb.read_event(Some(Duration::zero()), self.raw)) {
+                Event::NoEvent => {}
+                event => return Ok(Async::Ready(Some(event)))
+            }
+
+            // Everything there was has been read, so wait for the descriptor to become
+            // readable again.
+            if let Async::Ready(()) = self.input.poll_read() {
+                let _ = self.input.need_read();
+            }
+
+            if !self.wait_for_timer() {
+                return Ok(Async::NotReady);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! POSIX declarations shared by everything talking to the system directly on Unix, with the
+//! values Linux and OS X agree on. The Linux console adds its own in `console::api`.
+
+#![allow(non_camel_case_types)]
+
+pub use libc::{c_int, c_short, c_void, size_t, ssize_t};
+
+#[cfg(target_os="linux")]
+pub type nfds_t = ::libc::c_ulong;
+#[cfg(not(target_os="linux"))]
+pub type nfds_t = ::libc::c_uint;
+
+pub type sighandler_t = size_t;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct pollfd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short
+}
+
+pub const POLLIN: c_short = 0x0001;
+
+pub const EINTR: c_int = 4;
+
+pub const SIGINT: c_int = 2;
+pub const SIGQUIT: c_int = 3;
+#[cfg(all(target_os="linux",
+          any(target_arch="x86", target_arch="x86_64", target_arch="arm", target_arch="aarch64")))]
+pub const SIGTSTP: c_int = 20;
+#[cfg(target_os="macos")]
+pub const SIGTSTP: c_int = 18;
+
+pub const SIG_DFL: sighandler_t = 0;
+
+extern "C" {
+    pub fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int;
+
+    pub fn read(fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t;
+
+    pub fn write(fd: c_int, buf: *const c_void, count: size_t) -> ssize_t;
+
+    pub fn close(fd: c_int) -> c_int;
+
+    pub fn pipe(fds: *mut c_int) -> c_int;
+
+    pub fn dup(fd: c_int) -> c_int;
+
+    pub fn dup2(fd: c_int, fd2: c_int) -> c_int;
+
+    pub fn signal(signum: c_int, handler: sighandler_t) -> sighandler_t;
+}
@@ -0,0 +1,172 @@
+use std::cmp;
+use time::{Duration, SteadyTime};
+
+/// Shortest interval of a repeating timer, so that one started with no interval at all still
+/// has its next deadline in the future once expired, rather than expiring on every call.
+const MIN_INTERVAL_MS: i64 = 1;
+
+/// Identifies a timer started with `RustBox::start_timer` or `start_repeating_timer`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TimerId(usize);
+
+struct Timer {
+    id: TimerId,
+    deadline: SteadyTime,
+    // Time between expiries of a repeating timer.
+    interval: Option<Duration>
+}
+
+/// The timers of a RustBox, expired by read_event.
+pub struct Timers {
+    timers: Vec<Timer>,
+    next_id: usize
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        Timers { timers: Vec::new(), next_id: 0 }
+    }
+
+    pub fn start(&mut self, delay: Duration, interval: Option<Duration>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+
+        let interval = interval.map(|interval| cmp::max(interval, Duration::milliseconds(MIN_INTERVAL_MS)));
+
+        self.timers.push(Timer { id: id, deadline: SteadyTime::now() + delay, interval: interval });
+        id
+    }
+
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    /// Time left until the nearest deadline, or None without timers.
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = SteadyTime::now();
+
+        self.timers.iter()
+            .map(|timer| if timer.deadline > now { timer.deadline - now } else { Duration::zero() })
+            .min()
+    }
+
+    /// `timeout` shortened to end at the nearest deadline.
+    pub fn clamp(&self, timeout: Option<Duration>) -> Option<Duration> {
+        // Backends wait whole milliseconds, rounded down. Waiting one more makes sure the
+        // deadline has passed once they return.
+        let remaining = self.remaining().map(|remaining| {
+            if remaining > Duration::zero() { remaining + Duration::milliseconds(1) } else { remaining }
+        });
+
+        match (timeout, remaining) {
+            (Some(timeout), Some(remaining)) => Some(if remaining < timeout { remaining } else { timeout }),
+            (None, remaining) => remaining,
+            (timeout, None) => timeout
+        }
+    }
+
+    /// The timer with the earliest deadline that has passed, if any. A one-shot timer is
+    /// removed, a repeating one is due again an interval after its deadline.
+    pub fn expire(&mut self) -> Option<TimerId> {
+        let now = SteadyTime::now();
+
+        let index = match self.timers.iter().enumerate()
+            .filter(|&(_, timer)| timer.deadline <= now)
+            .min_by_key(|&(_, timer)| timer.deadline)
+        {
+            Some((index, _)) => index,
+            None => return None
+        };
+
+        let id = self.timers[index].id;
+
+        match self.timers[index].interval {
+            Some(interval) => {
+                // Deadlines are kept to the original schedule so that the timer does not drift,
+                // but ones missed entirely are skipped rather than reported late in a burst.
+                let timer = &mut self.timers[index];
+                timer.deadline = timer.deadline + interval;
+                while timer.deadline <= now {
+                    timer.deadline = timer.deadline + interval;
+                }
+            }
+            None => { self.timers.remove(index); }
+        }
+
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timers;
+    use time::{Duration, SteadyTime};
+
+    #[test]
+    fn one_shot_expires_once() {
+        let mut timers = Timers::new();
+        let id = timers.start(Duration::zero(), None);
+
+        assert_eq!(timers.expire(), Some(id));
+        assert_eq!(timers.expire(), None);
+        assert_eq!(timers.remaining(), None);
+    }
+
+    #[test]
+    fn earliest_deadline_first() {
+        let mut timers = Timers::new();
+        let later = timers.start(Duration::zero(), None);
+        let earlier = timers.start(Duration::zero(), None);
+        let pending = timers.start(Duration::seconds(60), None);
+        timers.timers[1].deadline = timers.timers[0].deadline - Duration::milliseconds(5);
+
+        assert_eq!(timers.expire(), Some(earlier));
+        assert_eq!(timers.expire(), Some(later));
+        assert_eq!(timers.expire(), None);
+
+        timers.cancel(pending);
+        assert_eq!(timers.remaining(), None);
+    }
+
+    #[test]
+    fn clamp() {
+        let mut timers = Timers::new();
+        assert_eq!(timers.clamp(None), None);
+        assert_eq!(timers.clamp(Some(Duration::milliseconds(10))), Some(Duration::milliseconds(10)));
+
+        timers.start(Duration::seconds(1), None);
+        assert_eq!(timers.clamp(Some(Duration::milliseconds(10))), Some(Duration::milliseconds(10)));
+
+        // Rounded up, so that waiting that long gets past the deadline.
+        let clamped = timers.clamp(None).unwrap();
+        assert!(clamped > Duration::milliseconds(990) && clamped <= Duration::milliseconds(1001));
+
+        timers.start(Duration::zero(), None);
+        assert_eq!(timers.clamp(Some(Duration::milliseconds(10))), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn repeating_skips_missed_deadlines() {
+        let mut timers = Timers::new();
+        let id = timers.start(Duration::zero(), Some(Duration::milliseconds(10)));
+        let now = SteadyTime::now();
+        let missed = now - Duration::milliseconds(35);
+        timers.timers[0].deadline = missed;
+
+        // Reported once for all the deadlines missed, and next due on the original schedule.
+        assert_eq!(timers.expire(), Some(id));
+        assert_eq!(timers.expire(), None);
+        let deadline = timers.timers[0].deadline;
+        assert!(deadline > now);
+        assert_eq!((deadline - missed).num_microseconds().unwrap() % 10000, 0);
+    }
+
+    #[test]
+    fn zero_interval() {
+        let mut timers = Timers::new();
+        let id = timers.start(Duration::zero(), Some(Duration::zero()));
+
+        assert_eq!(timers.expire(), Some(id));
+        assert_eq!(timers.expire(), None);
+    }
+}